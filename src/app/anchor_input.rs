@@ -1,15 +1,16 @@
 use std::{borrow::Borrow, cell::RefMut};
 
 use iced::{
-    theme,
+    theme::{self, Palette},
     widget::{button, pick_list, row, text, text_input},
-    Element, Length,
+    Element, Length, Theme,
 };
 
 use iced_lazy::Component;
 
 use strum::IntoEnumIterator;
 
+use super::i18n::tr;
 use super::model::*;
 
 use super::uitl::AWESOME;
@@ -19,6 +20,8 @@ pub struct AnchorInput<'a, Message> {
     on_submit: Option<Box<dyn Fn(AnchorInfo) -> Message>>,
     on_flush: Option<Box<dyn Fn() -> Message>>,
     on_setting: Option<Box<dyn Fn() -> Message>>,
+    on_select: Option<Box<dyn Fn() -> Message>>,
+    palette: Palette,
 }
 
 #[derive(Default)]
@@ -35,12 +38,21 @@ pub enum AnchorInputMessage {
     OnSubmit,
     OnFlush,
     OnSetting,
+    OnSelect,
+}
+
+// 下拉框中的直链选项, 其余选项为平台名
+const URL_SOURCE: &str = "Url";
+
+pub fn input_id() -> text_input::Id {
+    text_input::Id::new("anchor_input")
 }
 
 impl<'a, Message> AnchorInput<'a, Message> {
     pub fn new(mut selected: RefMut<'a, AnchorInputState>) -> Self {
         let plat = Platform::iter()
             .map(|e| format!("{:?}", e))
+            .chain([URL_SOURCE.to_string()])
             .collect::<Vec<_>>();
 
         if selected.borrow().pick_list_selected.is_empty() {
@@ -53,6 +65,8 @@ impl<'a, Message> AnchorInput<'a, Message> {
             on_submit: None,
             on_flush: None,
             on_setting: None,
+            on_select: None,
+            palette: Theme::Light.palette(),
         }
     }
 
@@ -68,6 +82,14 @@ impl<'a, Message> AnchorInput<'a, Message> {
         self.on_setting = Some(Box::new(f));
         self
     }
+    pub fn on_select<F: 'static + Fn() -> Message>(mut self, f: F) -> Self {
+        self.on_select = Some(Box::new(f));
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
 }
 
 impl<'a, Message> Component<Message, iced::Renderer> for AnchorInput<'a, Message> {
@@ -87,19 +109,25 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorInput<'a, Message
             }
             AnchorInputMessage::OnSubmit => {
                 log::info!("submit {}", self.state.input);
-                if self.state.input.is_empty() {
+                if self.state.input.trim().is_empty() {
                     return None;
                 }
-                let r = if let Some(cb) = &self.on_submit {
-                    Some(cb(AnchorInfo {
-                        name: self.state.input.clone(),
-                        platform: Some(self.state.pick_list_selected.as_str().into()),
-                        room_id: self.state.input.clone(),
-                        show_type: None,
-                    }))
+                let input = self.state.input.trim().to_string();
+                let anchor = if self.state.pick_list_selected == URL_SOURCE {
+                    AnchorInfo {
+                        name: input.clone(),
+                        url: Some(input),
+                        ..Default::default()
+                    }
                 } else {
-                    None
+                    AnchorInfo {
+                        name: input.clone(),
+                        platform: self.state.pick_list_selected.parse().ok(),
+                        room_id: input,
+                        ..Default::default()
+                    }
                 };
+                let r = self.on_submit.as_ref().map(|cb| cb(anchor));
 
                 self.state.input.clear();
                 r
@@ -116,6 +144,12 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorInput<'a, Message
                 }
                 None
             }
+            AnchorInputMessage::OnSelect => {
+                if let Some(cb) = &self.on_select {
+                    return Some(cb());
+                }
+                None
+            }
         };
 
         r
@@ -128,19 +162,30 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorInput<'a, Message
             AnchorInputMessage::Selected,
         );
 
-        let input = text_input("room id", &self.state.input)
+        let placeholder = if self.state.pick_list_selected == URL_SOURCE {
+            tr("input.url")
+        } else {
+            tr("input.room_id")
+        };
+        let input = text_input(placeholder, &self.state.input)
+            .id(input_id())
             .on_input(AnchorInputMessage::OnInput)
             .on_submit(AnchorInputMessage::OnSubmit);
 
-        let flush = button(text("\u{f021}").font(AWESOME))
+        let icon = |c: &'static str| text(c).font(AWESOME).style(self.palette.text);
+        let flush = button(icon("\u{f021}"))
             .style(theme::Button::Secondary)
             .on_press(AnchorInputMessage::OnFlush);
 
-        let setting = button(text("\u{f013}").font(AWESOME))
+        let setting = button(icon("\u{f013}"))
             .style(theme::Button::Secondary)
             .on_press(AnchorInputMessage::OnSetting);
 
-        row!(pick, input.width(Length::Fill), flush, setting)
+        let select = button(icon("\u{f14a}"))
+            .style(theme::Button::Secondary)
+            .on_press(AnchorInputMessage::OnSelect);
+
+        row!(pick, input.width(Length::Fill), flush, select, setting)
             .spacing(10)
             .align_items(iced::Alignment::Center)
             .into()
@@ -1,22 +1,34 @@
+use std::time::SystemTime;
+
 use iced::{
-    theme,
-    widget::{button, row, text, text_input},
-    Element, Length,
+    theme::{self, Palette},
+    widget::{button, checkbox, image, pick_list, row, text, text_input},
+    Element, Length, Theme,
 };
 use iced_lazy::Component;
 use iced_native::column;
+use strum::IntoEnumIterator;
 
 use super::{
-    model::{self, ShowType},
-    uitl::AWESOME,
+    i18n::tr,
+    model::{self, Platform, ShowType},
+    mpv::{PlaybackState, PlayerControl},
+    uitl::{format_age, AWESOME},
 };
 
 pub struct AnchorItem<'a, Message> {
     info: &'a model::AnchorInfo,
     on_play: Option<Box<dyn Fn(model::Node) -> Message>>,
     on_update: Option<Box<dyn Fn(AnchorItemUpdateType) -> Message>>,
-    show_edit: bool,
-    name_editor: String,
+    on_line_switch: Option<Box<dyn Fn(i32) -> Message>>,
+    on_check: Option<Box<dyn Fn(bool) -> Message>>,
+    // 多选模式下显示勾选框
+    checked: Option<bool>,
+    palette: Palette,
+    thumbnail: Option<image::Handle>,
+    // mpv 窗口打开时的播放状态
+    playback: Option<PlaybackState>,
+    on_player_control: Option<Box<dyn Fn(PlayerControl) -> Message>>,
 }
 
 #[derive(Debug, Clone)]
@@ -27,10 +39,33 @@ pub enum AnchorItemMessage {
     OnDel,
     OnEditSubmit,
     OnEditInput(String),
+    OnEditPlatform(Platform),
+    OnEditRoom(String),
+    OnEditNotes(String),
+    OnEditMetaKey(usize, String),
+    OnEditMetaValue(usize, String),
+    OnMetaAdd,
+    OnMetaDel(usize),
     OnLiveLineSwitch(i32),
+    OnCheck(bool),
+    OnPlayerControl(PlayerControl),
     None(String),
 }
 
+// 编辑状态放在组件 State 中, 列表重新渲染时不会丢失。
+// iced_lazy 按位置保存 State, 记录所编辑主播的 key, 位置上换成其他主播时不再使用
+#[derive(Default)]
+pub struct EditState {
+    show: bool,
+    key: String,
+    name: String,
+    platform: Option<Platform>,
+    // 直链主播编辑的是地址
+    room: String,
+    notes: String,
+    metadata: Vec<(String, String)>,
+}
+
 #[derive(Debug, Clone)]
 pub enum AnchorItemUpdateType {
     Del,
@@ -43,8 +78,13 @@ impl<'a, Message> AnchorItem<'a, Message> {
             info,
             on_play: None,
             on_update: None,
-            show_edit: false,
-            name_editor: "".into(),
+            on_line_switch: None,
+            on_check: None,
+            checked: None,
+            palette: Theme::Light.palette(),
+            thumbnail: None,
+            playback: None,
+            on_player_control: None,
         }
     }
     pub fn on_play<F: 'static + Fn(model::Node) -> Message>(mut self, f: F) -> Self {
@@ -55,71 +95,195 @@ impl<'a, Message> AnchorItem<'a, Message> {
         self.on_update = Some(Box::new(f));
         self
     }
+    pub fn on_line_switch<F: 'static + Fn(i32) -> Message>(mut self, f: F) -> Self {
+        self.on_line_switch = Some(Box::new(f));
+        self
+    }
+    pub fn on_check<F: 'static + Fn(bool) -> Message>(mut self, f: F) -> Self {
+        self.on_check = Some(Box::new(f));
+        self
+    }
+    pub fn checked(mut self, checked: Option<bool>) -> Self {
+        self.checked = checked;
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    pub fn thumbnail(mut self, handle: Option<image::Handle>) -> Self {
+        self.thumbnail = handle;
+        self
+    }
+    pub fn playback(mut self, playback: Option<PlaybackState>) -> Self {
+        self.playback = playback;
+        self
+    }
+    pub fn on_player_control<F: 'static + Fn(PlayerControl) -> Message>(mut self, f: F) -> Self {
+        self.on_player_control = Some(Box::new(f));
+        self
+    }
+}
+impl<'a, Message> AnchorItem<'a, Message> {
+    fn playback_view(&self, p: &PlaybackState) -> Element<'_, AnchorItemMessage> {
+        let status = if p.buffering {
+            text(tr("item.buffering")).style(self.palette.danger)
+        } else {
+            text(tr("item.playing")).style(self.palette.success)
+        };
+        let dropped = match p.dropped_frames {
+            Some(n) => text(tr("item.dropped").replace("{}", &n.to_string())),
+            None => text(""),
+        };
+        let control = |icon: char, c: PlayerControl| {
+            button(text(icon).font(AWESOME).size(14))
+                .style(theme::Button::Text)
+                .on_press(AnchorItemMessage::OnPlayerControl(c))
+        };
+        let mute = if p.mute { '\u{f6a9}' } else { '\u{f026}' };
+        row!(
+            status.size(14),
+            dropped.size(14),
+            row!().width(Length::Fill),
+            control(mute, PlayerControl::ToggleMute),
+            control('\u{f027}', PlayerControl::VolumeDown),
+            text(format!("{:.0}%", p.volume)).size(14),
+            control('\u{f028}', PlayerControl::VolumeUp),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+        .into()
+    }
 }
+
 impl<'a, Message> Component<Message, iced::Renderer> for AnchorItem<'a, Message> {
-    type State = AnchorItemState;
+    type State = EditState;
 
     type Event = AnchorItemMessage;
 
     fn update(&mut self, state: &mut Self::State, event: Self::Event) -> Option<Message> {
+        if state.show && state.key != self.info.key() {
+            // 筛选、删除或撤销后该位置是另一个主播, 丢弃之前的编辑
+            *state = EditState::default();
+        }
         match event {
             AnchorItemMessage::OnPlay => {
                 if let Some(cb) = self.on_play.as_ref() {
-                    if let Some(ShowType::On(seam_info)) = &self.info.show_type {
-                        if let Some(nodes) = &seam_info.nodes {
-                            if let Some(n) = nodes.get(state.live_line) {
-                                return Some(cb(n.clone()));
-                            }
-                            return None;
-                        }
+                    if let Some(n) = self.info.live_node() {
+                        return Some(cb(n.clone()));
                     }
                 }
                 None
             }
             AnchorItemMessage::OnEdit => {
-                self.name_editor = self.info.name.clone();
-                self.show_edit = true;
+                *state = EditState {
+                    show: true,
+                    key: self.info.key(),
+                    name: self.info.name.clone(),
+                    platform: self.info.platform,
+                    room: match &self.info.url {
+                        Some(url) => url.clone(),
+                        None => self.info.room_id.clone(),
+                    },
+                    notes: self.info.notes.clone(),
+                    metadata: self
+                        .info
+                        .metadata
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
+                };
                 None
             }
             AnchorItemMessage::CloseEdit => {
-                self.show_edit = false;
+                state.show = false;
                 None
             }
             AnchorItemMessage::OnDel => {
-                self.show_edit = false;
+                state.show = false;
                 if let Some(cb) = self.on_update.as_ref() {
                     return cb(AnchorItemUpdateType::Del).into();
                 }
                 None
             }
             AnchorItemMessage::OnEditSubmit => {
-                self.show_edit = false;
-                if self.name_editor == self.info.name {
+                let room = state.room.trim();
+                if room.is_empty() {
+                    return None;
+                }
+                state.show = false;
+                let mut new_item = self.info.clone();
+                new_item.name = state.name.clone();
+                if new_item.url.is_some() {
+                    new_item.url = Some(room.to_string());
+                } else {
+                    new_item.room_id = room.to_string();
+                    if state.platform.is_some() {
+                        new_item.platform = state.platform;
+                        new_item.unsupported_platform = None;
+                    }
+                }
+                new_item.notes = state.notes.trim().to_string();
+                new_item.metadata = state
+                    .metadata
+                    .iter()
+                    .filter(|(k, _)| !k.trim().is_empty())
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .collect();
+                if new_item.same_saved(self.info) {
                     return None;
                 }
                 if let Some(cb) = self.on_update.as_ref() {
-                    let mut new_item = self.info.clone();
-                    new_item.name = self.name_editor.clone();
                     return cb(AnchorItemUpdateType::Update(new_item)).into();
                 }
                 None
             }
             AnchorItemMessage::OnEditInput(s) => {
-                self.name_editor = s;
+                state.name = s;
+                None
+            }
+            AnchorItemMessage::OnEditPlatform(p) => {
+                state.platform = Some(p);
+                None
+            }
+            AnchorItemMessage::OnEditRoom(s) => {
+                state.room = s;
+                None
+            }
+            AnchorItemMessage::OnEditNotes(s) => {
+                state.notes = s;
+                None
+            }
+            AnchorItemMessage::OnEditMetaKey(i, s) => {
+                if let Some(m) = state.metadata.get_mut(i) {
+                    m.0 = s;
+                }
+                None
+            }
+            AnchorItemMessage::OnEditMetaValue(i, s) => {
+                if let Some(m) = state.metadata.get_mut(i) {
+                    m.1 = s;
+                }
+                None
+            }
+            AnchorItemMessage::OnMetaAdd => {
+                state.metadata.push(Default::default());
+                None
+            }
+            AnchorItemMessage::OnMetaDel(i) => {
+                if i < state.metadata.len() {
+                    state.metadata.remove(i);
+                }
                 None
             }
+            AnchorItemMessage::OnCheck(b) => self.on_check.as_ref().map(|cb| cb(b)),
+            AnchorItemMessage::OnPlayerControl(c) => {
+                self.on_player_control.as_ref().map(|cb| cb(c))
+            }
             AnchorItemMessage::None(_) => None,
             AnchorItemMessage::OnLiveLineSwitch(i) => {
-                if let Some(ShowType::On(s)) = &self.info.show_type {
-                    if let Some(n) = &s.nodes {
-                        if !n.is_empty() {
-                            if i > 0 {
-                                state.live_line = (state.live_line + 1) % n.len();
-                            } else {
-                                state.live_line = (state.live_line + n.len() - 1) % n.len();
-                            }
-                        }
-                    }
+                if let Some(cb) = self.on_line_switch.as_ref() {
+                    return Some(cb(i));
                 }
                 None
             }
@@ -127,44 +291,101 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorItem<'a, Message>
     }
 
     fn view(&self, state: &Self::State) -> iced_native::Element<'_, Self::Event, iced::Renderer> {
-        if !self.show_edit {
-            let name = format!(
-                "{}:{}",
-                self.info.platform.as_ref().unwrap().as_ui_text(),
-                self.info.name
-            );
+        if !state.show || state.key != self.info.key() {
+            let name = if self.info.group.is_empty() {
+                format!("{}:{}", self.info.source_text(), self.info.name)
+            } else {
+                format!(
+                    "[{}] {}:{}",
+                    self.info.group,
+                    self.info.source_text(),
+                    self.info.name
+                )
+            };
             let room = text(name);
 
-            let mut play = button(text("观看"));
+            let mut play = button(text(tr("item.watch")));
             let mut title = text("");
 
-            let status = if let Some(ShowType::On(s)) = &self.info.show_type {
-                play = play.on_press(AnchorItemMessage::OnPlay);
-                title = text(&s.title);
-                text("直播中:")
+            let status = match &self.info.show_type {
+                Some(ShowType::On(s)) => {
+                    play = play.on_press(AnchorItemMessage::OnPlay);
+                    title = text(&s.title);
+                    text(tr("item.live")).style(self.palette.success)
+                }
+                Some(ShowType::Error(e)) => {
+                    title = text(e).style(self.palette.danger);
+                    text(tr("item.error")).style(self.palette.danger)
+                }
+                Some(ShowType::Off) => text(tr("item.off")),
+                _ if !self.info.is_supported() => {
+                    text(tr("item.unsupported")).style(self.palette.danger)
+                }
+                _ if self.info.checking => text(tr("item.checking")),
+                _ => text(tr("item.pending")),
+            };
+
+            let age: Element<AnchorItemMessage> = if self.info.checking {
+                text('\u{f110}').font(AWESOME).size(14).into()
+            } else if let Some(t) = self.info.updated_at {
+                text(format_age(t, SystemTime::now())).size(14).into()
             } else {
-                text("未开播")
+                row!().into()
             };
 
             let edit = button(text('\u{f304}').font(AWESOME).size(17))
                 .style(theme::Button::Text)
                 .on_press(AnchorItemMessage::OnEdit);
 
-            column!(
+            let mut info = column!(
                 row!(room, row!().width(Length::Fill), play, edit)
                     .spacing(5)
                     .align_items(iced::Alignment::Center),
-                row!(status, title)
+                row!(status, title, row!().width(Length::Fill), age)
                     .spacing(5)
                     .align_items(iced::Alignment::Center)
             )
             .spacing(3)
-            .align_items(iced::Alignment::Start)
-            .into()
+            .align_items(iced::Alignment::Start);
+            if !self.info.notes.is_empty() {
+                info = info.push(text(&self.info.notes).size(14));
+            }
+            if let Some(p) = &self.playback {
+                info = info.push(self.playback_view(p));
+            }
+
+            let mut r = row!().spacing(8).align_items(iced::Alignment::Center);
+            if let Some(c) = self.checked {
+                r = r.push(checkbox("", c, AnchorItemMessage::OnCheck));
+            }
+            if let Some(h) = &self.thumbnail {
+                r = r.push(
+                    image(h.clone())
+                        .width(Length::Fixed(80.0))
+                        .height(Length::Fixed(45.0)),
+                );
+            }
+            r.push(info).into()
         } else {
-            let edit_name = text_input("", &self.name_editor)
+            let edit_name = text_input("", &state.name)
                 .on_input(AnchorItemMessage::OnEditInput)
                 .on_submit(AnchorItemMessage::OnEditSubmit);
+            let edit_room = text_input("", &state.room)
+                .on_input(AnchorItemMessage::OnEditRoom)
+                .on_submit(AnchorItemMessage::OnEditSubmit);
+            let source: Element<AnchorItemMessage> = if self.info.url.is_some() {
+                text(tr("platform.url")).into()
+            } else {
+                pick_list(
+                    Platform::iter().collect::<Vec<_>>(),
+                    state.platform,
+                    AnchorItemMessage::OnEditPlatform,
+                )
+                .into()
+            };
+            let save = button(text("\u{f00c}").font(AWESOME))
+                .style(theme::Button::Primary)
+                .on_press(AnchorItemMessage::OnEditSubmit);
             let del = button(text("\u{f1f8}").font(AWESOME))
                 .style(theme::Button::Destructive)
                 .on_press(AnchorItemMessage::OnDel);
@@ -172,7 +393,7 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorItem<'a, Message>
                 .style(theme::Button::Text)
                 .on_press(AnchorItemMessage::CloseEdit);
 
-            let live_line_title = text(format!("线路{}:", state.live_line + 1));
+            let live_line_title = text(format!("{}{}:", tr("item.line"), self.info.live_line + 1));
             let mut live_line_format = text("");
             let mut live_line_input = text_input("", "");
 
@@ -182,20 +403,62 @@ impl<'a, Message> Component<Message, iced::Renderer> for AnchorItem<'a, Message>
             let live_line_switch_next = button(text("\u{f054}").font(AWESOME))
                 .style(theme::Button::Text)
                 .on_press(AnchorItemMessage::OnLiveLineSwitch(1));
-            if let Some(ShowType::On(s)) = &self.info.show_type {
-                if let Some(nodes) = &s.nodes {
-                    if let Some(node) = nodes.get(state.live_line) {
-                        live_line_input =
-                            text_input("", &node.url).on_input(AnchorItemMessage::None);
-                        live_line_format = text(&node.format);
-                    }
-                }
+            if let Some(node) = self.info.live_node() {
+                live_line_input = text_input("", &node.url).on_input(AnchorItemMessage::None);
+                live_line_format = text(&node.format);
             }
 
+            let notes = row!(
+                text(tr("item.notes")),
+                text_input("", &state.notes)
+                    .on_input(AnchorItemMessage::OnEditNotes)
+                    .on_submit(AnchorItemMessage::OnEditSubmit)
+                    .width(Length::Fill),
+                button(text("\u{f067}").font(AWESOME))
+                    .style(theme::Button::Text)
+                    .on_press(AnchorItemMessage::OnMetaAdd),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            let metadata =
+                state
+                    .metadata
+                    .iter()
+                    .enumerate()
+                    .fold(column!().spacing(3), |c, (i, (k, v))| {
+                        c.push(
+                            row!(
+                                text_input(tr("item.meta_key"), k)
+                                    .on_input(move |s| AnchorItemMessage::OnEditMetaKey(i, s))
+                                    .width(Length::FillPortion(1)),
+                                text_input(tr("item.meta_value"), v)
+                                    .on_input(move |s| AnchorItemMessage::OnEditMetaValue(i, s))
+                                    .on_submit(AnchorItemMessage::OnEditSubmit)
+                                    .width(Length::FillPortion(2)),
+                                button(text("\u{f00d}").font(AWESOME))
+                                    .style(theme::Button::Text)
+                                    .on_press(AnchorItemMessage::OnMetaDel(i)),
+                            )
+                            .spacing(5)
+                            .align_items(iced::Alignment::Center),
+                        )
+                    });
+
             column!(
-                row!(text("名称:"), edit_name.width(Length::Fill), del, close)
+                row!(
+                    text(tr("item.name")),
+                    edit_name.width(Length::Fill),
+                    save,
+                    del,
+                    close
+                )
+                .spacing(5)
+                .align_items(iced::Alignment::Center),
+                row!(source, edit_room.width(Length::Fill))
                     .spacing(5)
                     .align_items(iced::Alignment::Center),
+                notes,
+                metadata,
                 row!(
                     live_line_title,
                     live_line_input.width(Length::Fill),
@@ -221,8 +484,3 @@ where
         iced_lazy::component(numeric_input)
     }
 }
-
-#[derive(Default, Clone, Debug)]
-pub struct AnchorItemState {
-    live_line: usize,
-}
@@ -1,16 +1,52 @@
 use iced::{
-    theme::Button,
-    widget::{button, text, text_input},
-    Element, Length,
+    theme::{Button, Palette},
+    widget::{button, checkbox, pick_list, text, text_input},
+    Element, Length, Theme,
 };
+use std::{cell::RefMut, collections::HashMap, fmt};
+
 use iced_lazy::Component;
 use iced_native::row;
+use strum::IntoEnumIterator;
 
-use super::uitl::{AppConfig, SavedState, AWESOME};
+use super::{
+    credentials::{CookieStatus, Credentials},
+    i18n::{tr, Lang},
+    model::Platform,
+    player::{self, PlayerPreset},
+    profile,
+    theme::ThemeMode,
+    uitl::{AppConfig, SavedState, AWESOME, SCALE_RANGE},
+};
 
 pub struct CfgPanel<'a, Message> {
+    state: RefMut<'a, CfgPanelState>,
     cfg: &'a AppConfig,
+    credentials: &'a Credentials,
+    cookie_status: &'a HashMap<Platform, CookieStatus>,
     on_update: Option<Box<dyn Fn(Option<AppConfig>) -> Message>>,
+    on_credentials: Option<Box<dyn Fn(Credentials) -> Message>>,
+    on_check_cookie: Option<Box<dyn Fn(Platform, String) -> Message>>,
+    profiles: &'a [String],
+    on_profile: Option<Box<dyn Fn(Option<String>) -> Message>>,
+    players: &'a [PlayerPreset],
+    on_detect_players: Option<Box<dyn Fn() -> Message>>,
+    // 当前保存的播放器路径是否无法执行
+    player_missing: bool,
+    palette: Palette,
+}
+
+/// 配置下拉框中的选项, None 为默认配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileChoice(Option<String>);
+
+impl fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(name) => f.write_str(name),
+            None => f.write_str(tr("cfg.profile_default")),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -18,6 +54,23 @@ pub enum CfgPanelMessage {
     OnSave,
     OnOff,
     OninputPlayerPath(String),
+    OnPlayerPreset(PlayerPreset),
+    OninputPlayerArgs(String),
+    OnMpvIpc(bool),
+    OnDetectPlayers,
+    OnThemeSelected(ThemeMode),
+    OnLangSelected(Lang),
+    OninputCustomThemePath(String),
+    OninputScaleFactor(String),
+    OnCompactMode(bool),
+    OninputProxy(String),
+    OnCookiePlatform(Platform),
+    OninputCookie(String),
+    OnCookieSave,
+    OnCookieCheck,
+    OnProfileSelected(ProfileChoice),
+    OninputProfileName(String),
+    OnProfileCreate,
     None,
 }
 
@@ -25,30 +78,101 @@ pub enum CfgPanelMessage {
 pub struct CfgPanelState {
     inited: bool,
     cfg: AppConfig,
+    scale_input: String,
+    creds: Credentials,
+    cookie_platform: Option<Platform>,
+    profile_input: String,
+    args_input: String,
+    // 修改播放器路径时检查, 不在 view 中查找文件
+    player_missing: bool,
+}
+
+impl CfgPanelState {
+    /// 设置页中有还没保存的修改
+    pub fn is_dirty(&self, cfg: &AppConfig) -> bool {
+        self.inited && self.cfg != *cfg
+    }
+
+    fn cookie_platform(&self) -> Platform {
+        self.cookie_platform.unwrap_or(Platform::BiliBili)
+    }
 }
 
 impl<'a, Message> CfgPanel<'a, Message> {
-    pub fn new(cfg: &'a AppConfig) -> Self {
+    pub fn new(
+        state: RefMut<'a, CfgPanelState>,
+        cfg: &'a AppConfig,
+        credentials: &'a Credentials,
+        cookie_status: &'a HashMap<Platform, CookieStatus>,
+    ) -> Self {
         Self {
+            state,
             cfg,
+            credentials,
+            cookie_status,
             on_update: None,
+            on_credentials: None,
+            on_check_cookie: None,
+            profiles: &[],
+            on_profile: None,
+            players: &[],
+            on_detect_players: None,
+            player_missing: false,
+            palette: Theme::Light.palette(),
         }
     }
     pub fn on_update<F: 'static + Fn(Option<AppConfig>) -> Message>(mut self, f: F) -> Self {
         self.on_update = Some(Box::new(f));
         self
     }
+    pub fn on_credentials<F: 'static + Fn(Credentials) -> Message>(mut self, f: F) -> Self {
+        self.on_credentials = Some(Box::new(f));
+        self
+    }
+    pub fn on_check_cookie<F: 'static + Fn(Platform, String) -> Message>(mut self, f: F) -> Self {
+        self.on_check_cookie = Some(Box::new(f));
+        self
+    }
+    pub fn profiles(mut self, profiles: &'a [String]) -> Self {
+        self.profiles = profiles;
+        self
+    }
+    pub fn on_profile<F: 'static + Fn(Option<String>) -> Message>(mut self, f: F) -> Self {
+        self.on_profile = Some(Box::new(f));
+        self
+    }
+    pub fn players(mut self, players: &'a [PlayerPreset]) -> Self {
+        self.players = players;
+        self
+    }
+    pub fn on_detect_players<F: 'static + Fn() -> Message>(mut self, f: F) -> Self {
+        self.on_detect_players = Some(Box::new(f));
+        self
+    }
+    pub fn player_missing(mut self, missing: bool) -> Self {
+        self.player_missing = missing;
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
 }
 
 impl<'a, Message> Component<Message, iced::Renderer> for CfgPanel<'a, Message> {
-    type State = CfgPanelState;
+    type State = ();
 
     type Event = CfgPanelMessage;
 
-    fn update(&mut self, state: &mut Self::State, event: Self::Event) -> Option<Message> {
+    fn update(&mut self, _state: &mut Self::State, event: Self::Event) -> Option<Message> {
+        let state = &mut *self.state;
         if !state.inited {
             state.inited = true;
             state.cfg = self.cfg.clone();
+            state.scale_input = self.cfg.window.scale_factor.to_string();
+            state.args_input = self.cfg.player_args.join(" ");
+            state.creds = self.credentials.clone();
+            state.player_missing = self.player_missing;
         }
         match event {
             CfgPanelMessage::OnOff => {
@@ -70,34 +194,262 @@ impl<'a, Message> Component<Message, iced::Renderer> for CfgPanel<'a, Message> {
                 None
             }
             CfgPanelMessage::OninputPlayerPath(s) => {
+                state.player_missing = player::find_executable(&s).is_none();
                 state.cfg.player_path = s;
                 None
             }
+            CfgPanelMessage::OnPlayerPreset(p) => {
+                state.cfg.player_path = p.path.to_string_lossy().into_owned();
+                state.player_missing = player::find_executable(&state.cfg.player_path).is_none();
+                state.cfg.player_args = p.args;
+                state.args_input = state.cfg.player_args.join(" ");
+                None
+            }
+            // 参数以空格分隔
+            CfgPanelMessage::OninputPlayerArgs(s) => {
+                state.cfg.player_args = s.split_whitespace().map(str::to_string).collect();
+                state.args_input = s;
+                None
+            }
+            CfgPanelMessage::OnDetectPlayers => {
+                let cb = self.on_detect_players.as_ref()?;
+                Some(cb())
+            }
+            CfgPanelMessage::OnThemeSelected(t) => {
+                state.cfg.theme = t;
+                None
+            }
+            CfgPanelMessage::OnLangSelected(l) => {
+                state.cfg.lang = l;
+                None
+            }
+            CfgPanelMessage::OninputCustomThemePath(s) => {
+                state.cfg.custom_theme_path = s;
+                None
+            }
+            CfgPanelMessage::OninputScaleFactor(s) => {
+                if let Ok(v) = s.trim().parse::<f64>() {
+                    if SCALE_RANGE.contains(&v) {
+                        state.cfg.window.scale_factor = v;
+                    }
+                }
+                state.scale_input = s;
+                None
+            }
+            CfgPanelMessage::OnMpvIpc(b) => {
+                state.cfg.mpv_ipc = b;
+                None
+            }
+            CfgPanelMessage::OnCompactMode(b) => {
+                state.cfg.compact_mode = b;
+                None
+            }
+            CfgPanelMessage::OninputProxy(s) => {
+                state.cfg.proxy.global = s;
+                None
+            }
+            CfgPanelMessage::OnCookiePlatform(p) => {
+                state.cookie_platform = Some(p);
+                None
+            }
+            CfgPanelMessage::OninputCookie(s) => {
+                let p = state.cookie_platform();
+                state.creds.cookies.insert(p, s);
+                None
+            }
+            CfgPanelMessage::OnCookieSave => {
+                let cb = self.on_credentials.as_ref()?;
+                let mut creds = state.creds.clone();
+                creds.cookies.retain(|_, c| !c.trim().is_empty());
+                Some(cb(creds))
+            }
+            CfgPanelMessage::OnCookieCheck => {
+                let cb = self.on_check_cookie.as_ref()?;
+                let p = state.cookie_platform();
+                let cookie = state.creds.cookie(p)?.to_string();
+                Some(cb(p, cookie))
+            }
+            CfgPanelMessage::OnProfileSelected(c) => {
+                let cb = self.on_profile.as_ref()?;
+                Some(cb(c.0))
+            }
+            CfgPanelMessage::OninputProfileName(s) => {
+                state.profile_input = s;
+                None
+            }
+            CfgPanelMessage::OnProfileCreate => {
+                let cb = self.on_profile.as_ref()?;
+                let name = state.profile_input.trim().to_string();
+                if !profile::valid_name(&name) {
+                    return None;
+                }
+                state.profile_input.clear();
+                Some(cb(Some(name)))
+            }
             CfgPanelMessage::None => None,
         }
     }
 
-    fn view(&self, state: &Self::State) -> iced_native::Element<'_, Self::Event, iced::Renderer> {
+    fn view(&self, _state: &Self::State) -> iced_native::Element<'_, Self::Event, iced::Renderer> {
+        let state = &*self.state;
         let cfg = if state.inited { &state.cfg } else { self.cfg };
 
-        let title = iced_native::row!(text("设置")
+        let title = iced_native::row!(text(tr("cfg.title"))
             .horizontal_alignment(iced::alignment::Horizontal::Center)
             .width(Length::Fill)
+            .style(self.palette.primary)
             .size(40));
 
-        let player_path = text("播放器路径:");
+        let player_path = text(tr("cfg.player_path"));
+        let player_preset = row!(
+            pick_list(
+                self.players,
+                self.players
+                    .iter()
+                    .find(|p| p.path.to_str() == Some(cfg.player_path.as_str()))
+                    .cloned(),
+                CfgPanelMessage::OnPlayerPreset,
+            )
+            .placeholder(tr("cfg.player_preset"))
+            .width(Length::Fill),
+            button(text("\u{f002}").font(AWESOME))
+                .style(Button::Secondary)
+                .on_press(CfgPanelMessage::OnDetectPlayers),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
         let player_path_input =
             text_input("", &cfg.player_path).on_input(CfgPanelMessage::OninputPlayerPath);
+        let missing = if state.inited {
+            state.player_missing
+        } else {
+            self.player_missing
+        };
+        let player_missing = text(if missing {
+            tr("cfg.player_missing")
+        } else {
+            ""
+        })
+        .size(14)
+        .style(self.palette.danger);
+        let player_args = tr("cfg.player_args");
+        let args_value = if state.inited {
+            state.args_input.clone()
+        } else {
+            cfg.player_args.join(" ")
+        };
+        let player_args_input =
+            text_input("--no-ytdl {url}", &args_value).on_input(CfgPanelMessage::OninputPlayerArgs);
+        let mpv_ipc = checkbox(tr("cfg.mpv_ipc"), cfg.mpv_ipc, CfgPanelMessage::OnMpvIpc);
+
+        let theme = tr("cfg.theme");
+        let theme_pick = pick_list(
+            ThemeMode::ALL,
+            Some(cfg.theme),
+            CfgPanelMessage::OnThemeSelected,
+        );
+        let custom_theme_path = tr("cfg.custom_theme_path");
+        let mut custom_theme_path_input = text_input("palette.json", &cfg.custom_theme_path);
+        if cfg.theme == ThemeMode::Custom {
+            custom_theme_path_input =
+                custom_theme_path_input.on_input(CfgPanelMessage::OninputCustomThemePath);
+        }
+
+        let lang = tr("cfg.lang");
+        let lang_pick = pick_list(Lang::ALL, Some(cfg.lang), CfgPanelMessage::OnLangSelected);
+
+        let scale = tr("cfg.scale_factor");
+        let scale_value = if state.inited {
+            state.scale_input.clone()
+        } else {
+            cfg.window.scale_factor.to_string()
+        };
+        let scale_input =
+            text_input("1.0", &scale_value).on_input(CfgPanelMessage::OninputScaleFactor);
+
+        let compact = checkbox(
+            tr("cfg.compact_mode"),
+            cfg.compact_mode,
+            CfgPanelMessage::OnCompactMode,
+        );
 
-        let config_path = "配置文件路径:";
+        let proxy = tr("cfg.proxy");
+        let proxy_input = text_input("socks5://127.0.0.1:1080", &cfg.proxy.global)
+            .on_input(CfgPanelMessage::OninputProxy);
+
+        let creds = if state.inited {
+            &state.creds
+        } else {
+            self.credentials
+        };
+        let cookie_platform = state.cookie_platform();
+        let cookie = tr("cfg.cookie");
+        let cookie_row = row!(
+            pick_list(
+                Platform::iter().collect::<Vec<_>>(),
+                Some(cookie_platform),
+                CfgPanelMessage::OnCookiePlatform,
+            ),
+            text_input(
+                "cookie",
+                creds
+                    .cookies
+                    .get(&cookie_platform)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            )
+            .password()
+            .on_input(CfgPanelMessage::OninputCookie)
+            .width(Length::Fill),
+            button(text("\u{f0c7}").font(AWESOME)).on_press(CfgPanelMessage::OnCookieSave),
+            button(text("\u{f00c}").font(AWESOME))
+                .style(Button::Secondary)
+                .on_press(CfgPanelMessage::OnCookieCheck),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+        let cookie_status = text(
+            self.cookie_status
+                .get(&cookie_platform)
+                .map(|s| s.as_ui_text())
+                .unwrap_or_default(),
+        )
+        .size(14);
+
+        let location = profile::current();
+        let config_path = text(if location.portable {
+            format!("{} {}", tr("cfg.config_path"), tr("cfg.portable"))
+        } else {
+            tr("cfg.config_path").to_string()
+        });
+        let profile = tr("cfg.profile");
+        let choices: Vec<ProfileChoice> = [None]
+            .into_iter()
+            .chain(self.profiles.iter().cloned().map(Some))
+            .map(ProfileChoice)
+            .collect();
+        let profile_row = row!(
+            pick_list(
+                choices,
+                Some(ProfileChoice(location.profile.clone())),
+                CfgPanelMessage::OnProfileSelected,
+            ),
+            text_input(tr("cfg.profile_new"), &state.profile_input)
+                .on_input(CfgPanelMessage::OninputProfileName)
+                .on_submit(CfgPanelMessage::OnProfileCreate)
+                .width(Length::Fill),
+            button(text("\u{f067}").font(AWESOME)).on_press(CfgPanelMessage::OnProfileCreate),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
         let config_path_input = text_input("", SavedState::path().to_str().unwrap())
             .on_input(|_| CfgPanelMessage::None);
 
-        let github = "项目地址:";
+        let github = tr("cfg.github");
         let github_input = text_input("", "https://github.com/kirito41dd/seamui")
             .on_input(|_| CfgPanelMessage::None);
 
-        let group = "群组:";
+        let group = tr("cfg.group");
         let group_input =
             text_input("", "https://t.me/seam_rust").on_input(|_| CfgPanelMessage::None);
 
@@ -118,9 +470,30 @@ impl<'a, Message> Component<Message, iced::Renderer> for CfgPanel<'a, Message> {
         iced_native::column!(
             title,
             player_path,
+            player_preset,
             player_path_input,
+            player_missing,
+            player_args,
+            player_args_input,
+            mpv_ipc,
+            theme,
+            theme_pick,
+            custom_theme_path,
+            custom_theme_path_input,
+            lang,
+            lang_pick,
+            scale,
+            scale_input,
+            compact,
+            proxy,
+            proxy_input,
+            cookie,
+            cookie_row,
+            cookie_status,
             config_path,
             config_path_input,
+            profile,
+            profile_row,
             github,
             github_input,
             group,
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, future::Future, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use super::{i18n::tr, model::Platform, proxy::http_client, uitl::SavedState};
+
+/// 各平台的登录 cookie, 单独保存在 credentials.json, 不和 seamui.json 放在一起
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Credentials {
+    #[serde(default)]
+    pub cookies: HashMap<Platform, String>,
+}
+
+impl Credentials {
+    pub fn path() -> PathBuf {
+        let mut p = SavedState::path();
+        p.set_file_name("credentials.json");
+        p
+    }
+
+    pub fn cookie(&self, platform: Platform) -> Option<&str> {
+        self.cookies
+            .get(&platform)
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+    }
+
+    /// 文件不存在时返回空
+    pub fn load_blocking() -> anyhow::Result<Credentials> {
+        match std::fs::read(Self::path()) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 路径在调用时确定, 切换配置前发起的保存仍写入原来的文件
+    pub fn save(self) -> impl Future<Output = anyhow::Result<()>> {
+        let path = Self::path();
+        async move { self.save_to(path).await }
+    }
+
+    // unix 下权限为 0600, windows 下依赖用户目录自身的权限
+    async fn save_to(&self, path: PathBuf) -> anyhow::Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        tokio::fs::create_dir_all(path.parent().expect("get dir")).await?;
+
+        // 先限制权限再通过同一个句柄写入内容
+        let mut opts = tokio::fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        opts.mode(0o600);
+        let mut file = opts.open(&path).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // 旧文件的权限不会被 mode 修改
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await?;
+        }
+        file.write_all(data.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CookieStatus {
+    Checking,
+    // 有效, 附带用户名
+    Valid(String),
+    Invalid,
+    // 该平台不支持检测
+    Unknown,
+    Error(String),
+}
+
+impl CookieStatus {
+    pub fn as_ui_text(&self) -> String {
+        match self {
+            CookieStatus::Checking => tr("cookie.checking").into(),
+            CookieStatus::Valid(u) => format!("{} {}", tr("cookie.valid"), u),
+            CookieStatus::Invalid => tr("cookie.invalid").into(),
+            CookieStatus::Unknown => tr("cookie.unknown").into(),
+            CookieStatus::Error(e) => format!("{} {}", tr("item.error"), e),
+        }
+    }
+}
+
+const BILI_NAV: &str = "https://api.bilibili.com/x/web-interface/nav";
+
+pub async fn check(platform: Platform, cookie: String, proxy: Option<String>) -> CookieStatus {
+    let r = match platform {
+        Platform::BiliBili => check_bili(BILI_NAV, &cookie, proxy.as_deref()).await,
+        _ => return CookieStatus::Unknown,
+    };
+    r.unwrap_or_else(|e| CookieStatus::Error(e.to_string()))
+}
+
+async fn check_bili(url: &str, cookie: &str, proxy: Option<&str>) -> anyhow::Result<CookieStatus> {
+    #[derive(Deserialize)]
+    struct Nav {
+        code: i32,
+        data: Option<NavData>,
+    }
+    #[derive(Deserialize)]
+    struct NavData {
+        #[serde(rename = "isLogin", default)]
+        is_login: bool,
+        #[serde(default)]
+        uname: String,
+    }
+
+    let nav: Nav = http_client(proxy)?
+        .get(url)
+        .header(reqwest::header::COOKIE, cookie)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(match nav.data {
+        Some(d) if nav.code == 0 && d.is_login => CookieStatus::Valid(d.uname),
+        _ => CookieStatus::Invalid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{check_bili, CookieStatus, Credentials};
+    use crate::app::{model::Platform, test_http};
+
+    #[tokio::test]
+    async fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("seamui-cred-{}.json", std::process::id()));
+        let cred = Credentials {
+            cookies: HashMap::from([(Platform::BiliBili, "SESSDATA=abc".to_string())]),
+        };
+        cred.save_to(path.clone()).await.unwrap();
+        let loaded: Credentials = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(loaded, cred);
+        assert_eq!(loaded.cookie(Platform::BiliBili), Some("SESSDATA=abc"));
+        assert_eq!(loaded.cookie(Platform::HuYa), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+
+            // 已存在的文件权限较宽时也会收紧
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            cred.save_to(path.clone()).await.unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_check_bili() {
+        let ok = test_http::spawn(
+            200,
+            br#"{"code":0,"data":{"isLogin":true,"uname":"tester"}}"#,
+        )
+        .await;
+        assert_eq!(
+            check_bili(&ok.url("/nav"), "SESSDATA=abc", None)
+                .await
+                .unwrap(),
+            CookieStatus::Valid("tester".into())
+        );
+        assert!(ok.requests.lock().unwrap()[0]
+            .to_ascii_lowercase()
+            .contains("cookie: sessdata=abc"));
+
+        let expired = test_http::spawn(200, br#"{"code":-101,"data":{"isLogin":false}}"#).await;
+        assert_eq!(
+            check_bili(&expired.url("/nav"), "SESSDATA=old", None)
+                .await
+                .unwrap(),
+            CookieStatus::Invalid
+        );
+    }
+}
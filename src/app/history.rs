@@ -0,0 +1,100 @@
+//! 本次运行期间的播放记录和开播状态变化, 只保存在内存中
+
+use std::{collections::VecDeque, time::SystemTime};
+
+use super::{hooks::LiveEvent, i18n::tr, model::AnchorInfo};
+
+// 最多保留的记录条数
+const CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryKind {
+    // 播放, 记录线路名
+    Played(String),
+    Live(LiveEvent),
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub at: SystemTime,
+    pub name: String,
+    pub source: String,
+    pub kind: HistoryKind,
+}
+
+impl HistoryEntry {
+    pub fn new(anchor: &AnchorInfo, kind: HistoryKind) -> Self {
+        HistoryEntry {
+            at: SystemTime::now(),
+            name: anchor.name.clone(),
+            source: match &anchor.url {
+                Some(url) => url.clone(),
+                None => format!("{} {}", anchor.source_text(), anchor.room_id),
+            },
+            kind,
+        }
+    }
+
+    pub fn as_ui_text(&self) -> String {
+        match &self.kind {
+            HistoryKind::Played(line) => format!("{} {}", tr("history.played"), line),
+            HistoryKind::Live(LiveEvent::Online) => tr("history.online").to_string(),
+            HistoryKind::Live(LiveEvent::Offline) => tr("history.offline").to_string(),
+            HistoryKind::Live(LiveEvent::Error) => tr("history.error").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() >= CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// 从新到旧
+    pub fn iter(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, HistoryEntry, HistoryKind, CAPACITY};
+    use crate::app::{hooks::LiveEvent, model::AnchorInfo};
+
+    #[test]
+    fn test_history() {
+        let a = AnchorInfo {
+            name: "a".into(),
+            room_id: "6".into(),
+            url: Some("http://x/a.m3u8".into()),
+            ..Default::default()
+        };
+        let mut h = History::default();
+        for _ in 0..CAPACITY {
+            h.push(HistoryEntry::new(&a, HistoryKind::Live(LiveEvent::Offline)));
+        }
+        h.push(HistoryEntry::new(&a, HistoryKind::Played("flv".into())));
+        assert_eq!(h.iter().count(), CAPACITY);
+        let last = h.iter().next().unwrap();
+        assert_eq!(last.kind, HistoryKind::Played("flv".into()));
+        assert_eq!(last.source, "http://x/a.m3u8");
+        h.clear();
+        assert!(h.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! 开播状态变化时执行的命令或 webhook
+
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tokio::process;
+
+use super::{
+    model::{AnchorInfo, ShowType},
+    proxy::http_client,
+    server::{backoff, jitter, ServerConfig},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LiveEvent {
+    Online,
+    Offline,
+    Error,
+}
+
+impl LiveEvent {
+    pub fn of(show: &ShowType) -> LiveEvent {
+        match show {
+            ShowType::On(_) => LiveEvent::Online,
+            ShowType::Off => LiveEvent::Offline,
+            ShowType::Error(_) => LiveEvent::Error,
+        }
+    }
+
+    /// 状态在 开播/未开播/错误 之间切换时返回新的事件, 首次查询不算
+    pub fn changed(prev: Option<&ShowType>, now: Option<&ShowType>) -> Option<LiveEvent> {
+        let prev = LiveEvent::of(prev?);
+        let now = LiveEvent::of(now?);
+        (prev != now).then_some(now)
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LiveEvent::Online => "online",
+            LiveEvent::Offline => "offline",
+            LiveEvent::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum HookAction {
+    // 通过 shell 执行, 主播信息在 SEAMUI_* 环境变量中
+    Command { command: String },
+    // POST json 到该地址
+    Webhook { url: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HookConfig {
+    // 触发的事件, 为空表示全部
+    #[serde(default)]
+    pub events: Vec<LiveEvent>,
+    #[serde(flatten)]
+    pub action: HookAction,
+}
+
+impl HookConfig {
+    pub fn applies_to(&self, event: LiveEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// 传给命令和 webhook 的主播信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookPayload {
+    pub event: &'static str,
+    pub name: String,
+    pub platform: String,
+    pub room: String,
+    pub title: String,
+    pub url: String,
+}
+
+impl HookPayload {
+    pub fn new(event: LiveEvent, anchor: &AnchorInfo) -> Self {
+        let title = match &anchor.show_type {
+            Some(ShowType::On(s)) => s.title.clone(),
+            Some(ShowType::Error(e)) => e.clone(),
+            _ => String::new(),
+        };
+        HookPayload {
+            event: event.as_str(),
+            name: anchor.name.clone(),
+            platform: anchor
+                .platform
+                .map(|p| p.as_seam_arg().to_string())
+                .unwrap_or_else(|| anchor.source_text().to_string()),
+            room: anchor.room_id.clone(),
+            title,
+            url: anchor
+                .live_node()
+                .map(|n| n.url.clone())
+                .or_else(|| anchor.url.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn env(&self) -> [(&'static str, &str); 6] {
+        [
+            ("SEAMUI_EVENT", self.event),
+            ("SEAMUI_NAME", &self.name),
+            ("SEAMUI_PLATFORM", &self.platform),
+            ("SEAMUI_ROOM", &self.room),
+            ("SEAMUI_TITLE", &self.title),
+            ("SEAMUI_URL", &self.url),
+        ]
+    }
+}
+
+/// 执行一个 hook, 失败时按 `cfg` 的重试次数和退避时间重试
+pub async fn run(
+    hook: HookConfig,
+    payload: HookPayload,
+    cfg: ServerConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    let mut attempt = 0;
+    loop {
+        let err = match run_once(&hook.action, &payload, proxy.as_deref()).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if attempt >= cfg.retries {
+            return Err(err);
+        }
+        let wait = backoff(&cfg, attempt, jitter());
+        log::warn!("hook {:?} err {}, retry in {:?}", hook.action, err, wait);
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+async fn run_once(
+    action: &HookAction,
+    payload: &HookPayload,
+    proxy: Option<&str>,
+) -> anyhow::Result<()> {
+    match action {
+        HookAction::Command { command } => {
+            let mut cmd = if cfg!(windows) {
+                let mut c = process::Command::new("cmd");
+                c.arg("/C");
+                c
+            } else {
+                let mut c = process::Command::new("sh");
+                c.arg("-c");
+                c
+            };
+            let status = cmd
+                .arg(command)
+                .envs(payload.env())
+                .stdin(Stdio::null())
+                .status()
+                .await?;
+            if !status.success() {
+                anyhow::bail!("{} exit {}", command, status);
+            }
+        }
+        HookAction::Webhook { url } => {
+            let resp = http_client(proxy)?.post(url).json(payload).send().await?;
+            if !resp.status().is_success() {
+                anyhow::bail!("{} http {}", url, resp.status());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, HookAction, HookConfig, HookPayload, LiveEvent};
+    use crate::app::{model::ShowType, server::ServerConfig, test_http};
+
+    fn payload() -> HookPayload {
+        HookPayload {
+            event: "online",
+            name: "主播".into(),
+            platform: "bili".into(),
+            room: "6".into(),
+            title: "标题".into(),
+            url: "http://x/a.flv".into(),
+        }
+    }
+
+    fn cfg() -> ServerConfig {
+        ServerConfig {
+            retries: 2,
+            backoff_ms: 1,
+            backoff_max_ms: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_changed() {
+        let off = ShowType::Off;
+        let err = ShowType::Error("e".into());
+        assert_eq!(LiveEvent::changed(None, Some(&off)), None);
+        assert_eq!(LiveEvent::changed(Some(&off), Some(&off)), None);
+        assert_eq!(
+            LiveEvent::changed(Some(&off), Some(&err)),
+            Some(LiveEvent::Error)
+        );
+        assert_eq!(LiveEvent::changed(Some(&off), None), None);
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        let ok = test_http::spawn(200, b"").await;
+        let hook = HookConfig {
+            events: vec![],
+            action: HookAction::Webhook {
+                url: ok.url("/hook"),
+            },
+        };
+        run(hook, payload(), cfg(), None).await.unwrap();
+        assert_eq!(ok.hits(), 1);
+        let req = ok.requests.lock().unwrap()[0].clone();
+        assert!(req.starts_with("POST /hook"));
+        assert!(req.contains(r#""name":"主播""#));
+        assert!(req.contains(r#""event":"online""#));
+
+        let broken = test_http::spawn(500, b"").await;
+        let hook = HookConfig {
+            events: vec![],
+            action: HookAction::Webhook {
+                url: broken.url("/hook"),
+            },
+        };
+        assert!(run(hook, payload(), cfg(), None).await.is_err());
+        assert_eq!(broken.hits(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command() {
+        let out = std::env::temp_dir().join(format!("seamui-hook-{}", std::process::id()));
+        let hook = HookConfig {
+            events: vec![LiveEvent::Online],
+            action: HookAction::Command {
+                command: format!(
+                    r#"echo "$SEAMUI_NAME $SEAMUI_ROOM $SEAMUI_URL" > {}"#,
+                    out.display()
+                ),
+            },
+        };
+        assert!(hook.applies_to(LiveEvent::Online));
+        assert!(!hook.applies_to(LiveEvent::Offline));
+        run(hook, payload(), cfg(), None).await.unwrap();
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(written.trim(), "主播 6 http://x/a.flv");
+        std::fs::remove_file(out).ok();
+
+        let fail = HookConfig {
+            events: vec![],
+            action: HookAction::Command {
+                command: "exit 3".into(),
+            },
+        };
+        assert!(run(fail, payload(), cfg(), None).await.is_err());
+    }
+}
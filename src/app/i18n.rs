@@ -0,0 +1,310 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Lang {
+    pub const ALL: &'static [Lang] = &[Lang::Zh, Lang::En];
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::Zh => ZH,
+            Lang::En => EN,
+        }
+    }
+}
+
+impl std::fmt::Display for Lang {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Lang::Zh => "中文",
+            Lang::En => "English",
+        })
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// 按当前语言查找文本, 缺失时回退到中文, 中文也缺失则返回 key 本身
+pub fn tr(key: &'static str) -> &'static str {
+    lookup(lang().catalog(), key)
+}
+
+fn lookup(catalog: &'static [(&'static str, &'static str)], key: &'static str) -> &'static str {
+    catalog
+        .iter()
+        .chain(ZH.iter())
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| *v)
+        .unwrap_or(key)
+}
+
+const ZH: &[(&str, &str)] = &[
+    ("platform.bili", "B站"),
+    ("platform.douyu", "斗鱼"),
+    ("platform.douyin", "抖音"),
+    ("platform.huya", "虎牙"),
+    ("platform.kuaishou", "快手"),
+    ("platform.cc", "CC"),
+    ("platform.huajiao", "花椒"),
+    ("platform.now", "Now"),
+    ("platform.afreeca", "Afreeca"),
+    ("platform.url", "直链"),
+    ("item.watch", "观看"),
+    ("item.live", "直播中:"),
+    ("item.error", "错误:"),
+    ("item.off", "未开播"),
+    ("item.name", "名称:"),
+    ("item.notes", "备注:"),
+    ("item.meta_key", "字段"),
+    ("item.meta_value", "内容"),
+    ("item.line", "线路"),
+    ("item.pending", "待查询"),
+    ("item.checking", "查询中"),
+    ("item.buffering", "缓冲中"),
+    ("item.playing", "播放中"),
+    ("item.dropped", "掉帧 {}"),
+    ("item.unsupported", "不支持的平台"),
+    ("input.room_id", "房间号"),
+    ("input.url", "直播流地址"),
+    ("main.confirm_delete", "确认删除"),
+    ("main.ok", "确定"),
+    ("main.add", "添加"),
+    ("main.cancel", "取消"),
+    ("main.querying", "查询中:"),
+    ("main.refreshing", "刷新中"),
+    ("main.undo", "撤销"),
+    ("undo.count", "{}个主播"),
+    ("main.filter", "筛选名称/房间号/分组/备注/自定义字段"),
+    ("bulk.import_path", "导入文件路径"),
+    ("bulk.import", "导入"),
+    ("bulk.imported", "已导入"),
+    ("bulk.select_all", "全选"),
+    ("bulk.select_filtered", "选择筛选结果"),
+    ("bulk.clear", "清空"),
+    ("bulk.selected", "已选"),
+    ("bulk.refresh", "刷新"),
+    ("bulk.play", "播放直播中"),
+    ("bulk.export", "导出"),
+    ("bulk.exported", "已导出到"),
+    ("bulk.group", "分组名"),
+    ("bulk.move_to_group", "移到分组"),
+    ("bulk.delete", "删除"),
+    ("undo.deleted", "已删除"),
+    ("undo.updated", "已修改"),
+    ("undo.moved", "已移动"),
+    ("undo.imported", "已导入"),
+    ("age.just_now", "刚刚"),
+    ("age.minutes", "{}分钟前"),
+    ("age.hours", "{}小时前"),
+    ("age.days", "{}天前"),
+    ("page.anchors", "主播"),
+    ("page.history", "历史"),
+    ("page.recordings", "录制"),
+    ("page.logs", "日志"),
+    ("page.settings", "设置"),
+    ("history.tip", "本次运行的播放记录和开播状态变化"),
+    ("history.empty", "暂无记录"),
+    ("history.played", "播放"),
+    ("history.online", "开播"),
+    ("history.offline", "下播"),
+    ("history.error", "查询出错"),
+    ("recordings.empty", "暂不支持录制, 这里将显示录制的文件"),
+    ("logs.filter", "筛选日志"),
+    (
+        "reload.conflict",
+        "seamui.json 已被外部修改, 与程序中的修改冲突:",
+    ),
+    ("reload.settings", "设置"),
+    ("reload.keep_local", "保留程序中的"),
+    ("reload.use_disk", "使用文件中的"),
+    ("cfg.title", "设置"),
+    ("cfg.player_path", "播放器路径:"),
+    ("cfg.player_preset", "检测到的播放器"),
+    ("cfg.player_missing", "找不到该播放器或没有执行权限"),
+    (
+        "cfg.player_args",
+        "播放器参数(空格分隔, {url} 为直播流地址, 不填时放在最后):",
+    ),
+    ("main.player_missing", "无法执行播放器"),
+    ("cfg.mpv_ipc", "mpv 切换线路时复用已打开的窗口"),
+    ("cfg.theme", "主题:"),
+    ("cfg.custom_theme_path", "自定义主题文件:"),
+    ("cfg.lang", "语言:"),
+    ("cfg.scale_factor", "界面缩放(0.5-4.0):"),
+    ("cfg.compact_mode", "紧凑模式(不显示封面)"),
+    ("cfg.proxy", "全局代理:"),
+    ("cfg.cookie", "平台 Cookie(单独保存在 credentials.json):"),
+    ("cookie.checking", "检测中..."),
+    ("cookie.valid", "有效, 用户:"),
+    ("cookie.invalid", "已失效"),
+    ("cookie.unknown", "该平台不支持检测"),
+    ("cfg.config_path", "配置文件路径:"),
+    ("cfg.portable", "(便携模式)"),
+    ("cfg.profile", "配置:"),
+    ("cfg.profile_default", "默认"),
+    ("cfg.profile_new", "新配置名"),
+    ("cfg.github", "项目地址:"),
+    ("cfg.group", "群组:"),
+    ("theme.light", "浅色"),
+    ("theme.dark", "深色"),
+    ("theme.system", "跟随系统"),
+    ("theme.custom", "自定义"),
+];
+
+const EN: &[(&str, &str)] = &[
+    ("platform.bili", "Bilibili"),
+    ("platform.douyu", "Douyu"),
+    ("platform.douyin", "Douyin"),
+    ("platform.huya", "Huya"),
+    ("platform.kuaishou", "Kuaishou"),
+    ("platform.huajiao", "Huajiao"),
+    ("platform.url", "URL"),
+    ("item.watch", "Watch"),
+    ("item.live", "Live:"),
+    ("item.error", "Error:"),
+    ("item.off", "Offline"),
+    ("item.name", "Name:"),
+    ("item.notes", "Notes:"),
+    ("item.meta_key", "key"),
+    ("item.meta_value", "value"),
+    ("item.line", "Line "),
+    ("item.pending", "Not checked"),
+    ("item.checking", "Checking"),
+    ("item.buffering", "Buffering"),
+    ("item.playing", "Playing"),
+    ("item.dropped", "{} dropped"),
+    ("item.unsupported", "Unsupported platform"),
+    ("input.room_id", "room id"),
+    ("input.url", "stream url"),
+    ("main.confirm_delete", "Delete"),
+    ("main.ok", "OK"),
+    ("main.add", "Add"),
+    ("main.cancel", "Cancel"),
+    ("main.querying", "Querying:"),
+    ("main.refreshing", "Refreshing"),
+    ("main.undo", "Undo"),
+    ("undo.count", "{} anchors"),
+    ("main.filter", "filter name/room/group/notes/metadata"),
+    ("bulk.import_path", "import file path"),
+    ("bulk.import", "Import"),
+    ("bulk.imported", "Imported"),
+    ("bulk.select_all", "All"),
+    ("bulk.select_filtered", "Filtered"),
+    ("bulk.clear", "None"),
+    ("bulk.selected", "Selected"),
+    ("bulk.refresh", "Refresh"),
+    ("bulk.play", "Play live"),
+    ("bulk.export", "Export"),
+    ("bulk.exported", "Exported to"),
+    ("bulk.group", "group"),
+    ("bulk.move_to_group", "Move to group"),
+    ("bulk.delete", "Delete"),
+    ("undo.deleted", "Deleted"),
+    ("undo.updated", "Edited"),
+    ("undo.moved", "Moved"),
+    ("undo.imported", "Imported"),
+    ("age.just_now", "just now"),
+    ("age.minutes", "{}m ago"),
+    ("age.hours", "{}h ago"),
+    ("age.days", "{}d ago"),
+    ("page.anchors", "Anchors"),
+    ("page.history", "History"),
+    ("page.recordings", "Recordings"),
+    ("page.logs", "Logs"),
+    ("page.settings", "Settings"),
+    (
+        "history.tip",
+        "Plays and live status changes in this session",
+    ),
+    ("history.empty", "Nothing yet"),
+    ("history.played", "Played"),
+    ("history.online", "Went live"),
+    ("history.offline", "Went offline"),
+    ("history.error", "Query failed"),
+    (
+        "recordings.empty",
+        "Recording is not supported yet, recorded files will show here",
+    ),
+    ("logs.filter", "filter logs"),
+    (
+        "reload.conflict",
+        "seamui.json changed on disk and conflicts with:",
+    ),
+    ("reload.settings", "settings"),
+    ("reload.keep_local", "Keep mine"),
+    ("reload.use_disk", "Use file"),
+    ("cfg.title", "Settings"),
+    ("cfg.player_path", "Player path:"),
+    ("cfg.player_preset", "Detected players"),
+    ("cfg.player_missing", "Player not found or not executable"),
+    (
+        "cfg.player_args",
+        "Player arguments (space separated, {url} is the stream, appended if absent):",
+    ),
+    ("main.player_missing", "Cannot run player"),
+    (
+        "cfg.mpv_ipc",
+        "Reuse the open mpv window when switching lines",
+    ),
+    ("cfg.theme", "Theme:"),
+    ("cfg.custom_theme_path", "Custom theme file:"),
+    ("cfg.lang", "Language:"),
+    ("cfg.scale_factor", "UI scale (0.5-4.0):"),
+    ("cfg.compact_mode", "Compact mode (no thumbnails)"),
+    ("cfg.proxy", "Global proxy:"),
+    (
+        "cfg.cookie",
+        "Platform cookies (stored in credentials.json):",
+    ),
+    ("cookie.checking", "Checking..."),
+    ("cookie.valid", "Valid, user:"),
+    ("cookie.invalid", "Expired"),
+    ("cookie.unknown", "Check not supported for this platform"),
+    ("cfg.config_path", "Config file:"),
+    ("cfg.portable", "(portable)"),
+    ("cfg.profile", "Profile:"),
+    ("cfg.profile_default", "default"),
+    ("cfg.profile_new", "new profile name"),
+    ("cfg.github", "Project:"),
+    ("cfg.group", "Group:"),
+    ("theme.light", "Light"),
+    ("theme.dark", "Dark"),
+    ("theme.system", "Follow system"),
+    ("theme.custom", "Custom"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{lookup, EN, ZH};
+
+    #[test]
+    fn test_lookup_fallback() {
+        assert_eq!(lookup(EN, "item.watch"), "Watch");
+        // 英文缺失的 key 回退到中文
+        assert_eq!(lookup(EN, "platform.cc"), "CC");
+        assert_eq!(lookup(&[], "item.off"), "未开播");
+        assert_eq!(lookup(EN, "no.such.key"), "no.such.key");
+
+        for (k, _) in EN {
+            assert!(ZH.iter().any(|(z, _)| z == k), "{} missing in zh", k);
+        }
+    }
+}
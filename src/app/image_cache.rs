@@ -0,0 +1,148 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use super::{proxy::http_client, uitl::SavedState};
+
+/// 封面/头像的磁盘缓存, 文件名为 url 的哈希, 超过 ttl 重新下载。
+/// 文件没有扩展名, 返回内容由调用方按数据判断图片格式
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    ttl: Duration,
+    proxy: Option<String>,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self {
+            dir,
+            ttl,
+            proxy: None,
+        }
+    }
+
+    pub fn proxy(mut self, proxy: Option<&str>) -> Self {
+        self.proxy = proxy.map(str::to_string);
+        self
+    }
+
+    pub fn default_dir() -> PathBuf {
+        let mut p = SavedState::path();
+        p.pop();
+        p.push("cache");
+        p.push("images");
+        p
+    }
+
+    pub fn path_of(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(url.as_bytes())))
+    }
+
+    pub async fn fetch(self, url: String) -> anyhow::Result<Vec<u8>> {
+        let path = self.path_of(&url);
+        if let Ok(meta) = tokio::fs::metadata(&path).await {
+            if self.is_fresh(meta.modified()?) {
+                return Ok(tokio::fs::read(&path).await?);
+            }
+        }
+
+        let data = http_client(self.proxy.as_deref())?
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, &data).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        log::debug!("cached image {} -> {:?}", url, path);
+        Ok(data.to_vec())
+    }
+
+    /// 删除过期文件, 返回删除数量
+    pub async fn purge(self) -> anyhow::Result<usize> {
+        let mut n = 0;
+        let mut dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = dir.next_entry().await? {
+            let modified = entry.metadata().await?.modified()?;
+            if !self.is_fresh(modified) {
+                tokio::fs::remove_file(entry.path()).await?;
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn is_fresh(&self, modified: SystemTime) -> bool {
+        match SystemTime::now().duration_since(modified) {
+            Ok(age) => age < self.ttl,
+            Err(_) => true,
+        }
+    }
+}
+
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ImageCache;
+    use crate::app::test_http;
+
+    #[tokio::test]
+    async fn test_fetch_cached() {
+        let server = test_http::spawn(200, b"fake png").await;
+        let dir = std::env::temp_dir().join(format!("seamui-img-{}", std::process::id()));
+        let cache = ImageCache::new(dir.clone(), Duration::from_secs(60));
+        let url = server.url("/cover.jpg");
+
+        let data = cache.clone().fetch(url.clone()).await.unwrap();
+        assert_eq!(data, b"fake png");
+        let cached = cache.clone().fetch(url.clone()).await.unwrap();
+        assert_eq!(cached, b"fake png");
+        assert_eq!(server.hits(), 1);
+
+        let expired = ImageCache::new(dir.clone(), Duration::ZERO);
+        expired.clone().fetch(url).await.unwrap();
+        assert_eq!(server.hits(), 2);
+        assert_eq!(expired.purge().await.unwrap(), 1);
+
+        let missing = test_http::spawn(404, b"").await;
+        assert!(cache.fetch(missing.url("/none.jpg")).await.is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    // 2x1 的 png, 左红右蓝
+    const PNG: &[u8] = b"\x89\x50\x4e\x47\x0d\x0a\x1a\x0a\x00\x00\x00\x0d\x49\x48\x44\x52\x00\x00\x00\x02\x00\x00\x00\x01\x08\x02\x00\x00\x00\x7b\x40\xe8\xdd\x00\x00\x00\x0d\x49\x44\x41\x54\x78\x9c\x63\xf8\xcf\x00\x04\xff\x01\x07\x00\x01\xff\xe2\x23\x9e\x59\x00\x00\x00\x00\x49\x45\x4e\x44\xae\x42\x60\x82";
+
+    #[tokio::test]
+    async fn test_decode_cached() {
+        let server = test_http::spawn(200, PNG).await;
+        let dir = std::env::temp_dir().join(format!("seamui-png-{}", std::process::id()));
+        let cache = ImageCache::new(dir.clone(), Duration::from_secs(60));
+        // 地址没有扩展名, 缓存文件也没有
+        let url = server.url("/cover?size=small");
+
+        cache.clone().fetch(url.clone()).await.unwrap();
+        let cached = cache.fetch(url).await.unwrap();
+        assert_eq!(server.hits(), 1);
+        let img = image::load_from_memory(&cached).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (2, 1));
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(img.get_pixel(1, 0).0, [0, 0, 255]);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,222 @@
+use iced::keyboard::{KeyCode, Modifiers};
+use serde::{Deserialize, Serialize};
+
+/// 快捷键配置, 格式如 `Ctrl+R`, `Shift+Delete`, `F5`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub refresh: String,
+    pub open_setting: String,
+    pub focus_input: String,
+    pub select_next: String,
+    pub select_prev: String,
+    pub play: String,
+    pub line_next: String,
+    pub line_prev: String,
+    pub delete: String,
+    pub move_up: String,
+    pub move_down: String,
+    pub undo: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            refresh: "Ctrl+R".into(),
+            open_setting: "Ctrl+,".into(),
+            focus_input: "Ctrl+N".into(),
+            select_next: "Down".into(),
+            select_prev: "Up".into(),
+            play: "Enter".into(),
+            line_next: "Right".into(),
+            line_prev: "Left".into(),
+            delete: "Delete".into(),
+            move_up: "Ctrl+Up".into(),
+            move_down: "Ctrl+Down".into(),
+            undo: "Ctrl+Z".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Refresh,
+    OpenSetting,
+    FocusInput,
+    SelectNext,
+    SelectPrev,
+    Play,
+    LineNext,
+    LinePrev,
+    Delete,
+    MoveUp,
+    MoveDown,
+    Undo,
+}
+
+impl KeyBindings {
+    pub fn action(&self, key: KeyCode, modifiers: Modifiers) -> Option<KeyAction> {
+        [
+            (&self.refresh, KeyAction::Refresh),
+            (&self.open_setting, KeyAction::OpenSetting),
+            (&self.focus_input, KeyAction::FocusInput),
+            (&self.select_next, KeyAction::SelectNext),
+            (&self.select_prev, KeyAction::SelectPrev),
+            (&self.play, KeyAction::Play),
+            (&self.line_next, KeyAction::LineNext),
+            (&self.line_prev, KeyAction::LinePrev),
+            (&self.delete, KeyAction::Delete),
+            (&self.move_up, KeyAction::MoveUp),
+            (&self.move_down, KeyAction::MoveDown),
+            (&self.undo, KeyAction::Undo),
+        ]
+        .into_iter()
+        .find(|(b, _)| parse_binding(b) == Some((modifiers, key)))
+        .map(|(_, a)| a)
+    }
+}
+
+pub fn parse_binding(s: &str) -> Option<(Modifiers, KeyCode)> {
+    let mut modifiers = Modifiers::empty();
+    let mut key = None;
+    for part in s.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= Modifiers::CTRL,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            "alt" => modifiers |= Modifiers::ALT,
+            "cmd" | "super" | "logo" => modifiers |= Modifiers::LOGO,
+            _ => key = Some(parse_key(part)?),
+        }
+    }
+    key.map(|k| (modifiers, k))
+}
+
+fn parse_key(s: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::A,
+        KeyCode::B,
+        KeyCode::C,
+        KeyCode::D,
+        KeyCode::E,
+        KeyCode::F,
+        KeyCode::G,
+        KeyCode::H,
+        KeyCode::I,
+        KeyCode::J,
+        KeyCode::K,
+        KeyCode::L,
+        KeyCode::M,
+        KeyCode::N,
+        KeyCode::O,
+        KeyCode::P,
+        KeyCode::Q,
+        KeyCode::R,
+        KeyCode::S,
+        KeyCode::T,
+        KeyCode::U,
+        KeyCode::V,
+        KeyCode::W,
+        KeyCode::X,
+        KeyCode::Y,
+        KeyCode::Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Key0,
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    const FUNCTIONS: [KeyCode; 12] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+    ];
+
+    let lower = s.to_ascii_lowercase();
+    let mut chars = lower.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(LETTERS[c as usize - 'a' as usize]),
+            '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+            ',' => Some(KeyCode::Comma),
+            '.' => Some(KeyCode::Period),
+            '/' => Some(KeyCode::Slash),
+            '-' => Some(KeyCode::Minus),
+            '=' => Some(KeyCode::Equals),
+            _ => None,
+        };
+    }
+    if let Some(n) = lower
+        .strip_prefix('f')
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        return FUNCTIONS.get(n.checked_sub(1)?).copied();
+    }
+    let key = match lower.as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Escape,
+        "delete" | "del" => KeyCode::Delete,
+        "backspace" => KeyCode::Back,
+        "space" => KeyCode::Space,
+        "tab" => KeyCode::Tab,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::keyboard::{KeyCode, Modifiers};
+
+    use super::{parse_binding, KeyAction, KeyBindings};
+
+    #[test]
+    fn test_parse_binding() {
+        assert_eq!(parse_binding("Ctrl+R"), Some((Modifiers::CTRL, KeyCode::R)));
+        assert_eq!(
+            parse_binding("ctrl + shift + f5"),
+            Some((Modifiers::CTRL | Modifiers::SHIFT, KeyCode::F5))
+        );
+        assert_eq!(
+            parse_binding("Ctrl+,"),
+            Some((Modifiers::CTRL, KeyCode::Comma))
+        );
+        assert_eq!(
+            parse_binding("Delete"),
+            Some((Modifiers::empty(), KeyCode::Delete))
+        );
+        assert_eq!(parse_binding("Ctrl+Nope"), None);
+        assert_eq!(parse_binding("Ctrl"), None);
+
+        let kb = KeyBindings::default();
+        assert_eq!(
+            kb.action(KeyCode::R, Modifiers::CTRL),
+            Some(KeyAction::Refresh)
+        );
+        assert_eq!(kb.action(KeyCode::R, Modifiers::empty()), None);
+    }
+}
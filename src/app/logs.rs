@@ -0,0 +1,77 @@
+//! 日志同时交给 env_logger 输出和保存在内存中, 日志页显示最近的记录
+
+use std::{collections::VecDeque, sync::Mutex, time::SystemTime};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// 最多保留的日志条数
+const CAPACITY: usize = 500;
+
+static LINES: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub at: SystemTime,
+    pub level: Level,
+    pub text: String,
+}
+
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl Logger {
+    // 不受 RUST_LOG 影响, 本程序 info 及以上的日志总是保存
+    fn capture(metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info && metadata.target().starts_with("seamui")
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata) || Self::capture(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        if Self::capture(record.metadata()) {
+            push(record.level(), record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+fn push(level: Level, text: String) {
+    let mut lines = LINES.lock().unwrap();
+    if lines.len() >= CAPACITY {
+        lines.pop_front();
+    }
+    lines.push_back(LogLine {
+        at: SystemTime::now(),
+        level,
+        text,
+    });
+}
+
+/// 替代 `env_logger::init`
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max = inner.filter().max(LevelFilter::Info);
+    if log::set_boxed_logger(Box::new(Logger { inner })).is_ok() {
+        log::set_max_level(max);
+    }
+}
+
+/// 最近的日志, 从新到旧
+pub fn recent() -> Vec<LogLine> {
+    LINES.lock().unwrap().iter().rev().cloned().collect()
+}
+
+pub fn clear() {
+    LINES.lock().unwrap().clear();
+}
@@ -1,52 +1,225 @@
-use std::{cell::RefCell, ops::IndexMut};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ops::IndexMut,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use log::{debug, info};
 use tokio::sync::mpsc;
 
-use iced::{Application, Command, Subscription};
+use iced::{
+    event,
+    keyboard::{self, KeyCode, Modifiers},
+    theme::Palette,
+    widget::{image, text_input},
+    window, Application, Command, Event, Subscription,
+};
 
 use crate::app::uitl::PlayState;
 
 use self::{
     anchor_input::AnchorInputState,
     anchor_item::AnchorItemUpdateType,
+    cfg_panel::CfgPanelState,
+    credentials::{CookieStatus, Credentials},
+    history::{History, HistoryEntry, HistoryKind},
+    i18n::tr,
+    image_cache::ImageCache,
+    keymap::KeyAction,
     model::*,
-    server::SeamServer,
-    uitl::{AppConfig, SavedState},
+    mpv::{PlaybackState, PlayerControl},
+    pages::Page,
+    player::PlayerPreset,
+    server::{SeamServer, ServerStats, ServerTask, TaskEvent},
+    theme::ThemeMode,
+    uitl::{AppConfig, SavedState, WindowConfig},
+    undo::{Change, UndoStack},
 };
 
 mod anchor_input;
 mod anchor_item;
 mod cfg_panel;
+mod credentials;
+mod history;
+mod hooks;
+mod i18n;
+mod image_cache;
+mod keymap;
+mod logs;
 mod model;
+mod mpv;
 mod pages;
+mod player;
+mod probe;
+mod profile;
+mod proxy;
+mod reload;
+mod resolver;
 mod server;
+#[cfg(test)]
+mod test_http;
+mod theme;
 mod uitl;
+mod undo;
 
 pub struct SeamUI {
     loaded: bool,
     anchor_list: Vec<AnchorInfo>,
     anchor_input_state: RefCell<AnchorInputState>,
-    task_sender: mpsc::UnboundedSender<(AnchorInfo, AppConfig)>,
-    result_receiver: RefCell<Option<mpsc::UnboundedReceiver<AnchorInfo>>>,
+    task_sender: mpsc::UnboundedSender<ServerTask>,
+    server_stats: Arc<ServerStats>,
+    result_receiver: RefCell<Option<mpsc::UnboundedReceiver<TaskEvent>>>,
     config: AppConfig,
-    show_setting: bool,
+    custom_palette: Option<Palette>,
+    // 跟随系统时使用, 在 Tick 和窗口获得焦点时更新
+    system_dark: bool,
+    page: Page,
+    cfg_panel_state: RefCell<CfgPanelState>,
+    selected: Option<usize>,
+    pending_delete: Option<usize>,
+    // anchor key -> (图片 url, 已加载的图片)
+    thumbnails: HashMap<String, (String, Option<image::Handle>)>,
+    refresh: Option<RefreshProgress>,
+    credentials: Credentials,
+    cookie_status: HashMap<Platform, CookieStatus>,
+    undo: UndoStack,
+    // 多选模式
+    select_mode: bool,
+    checked: HashSet<usize>,
+    filter: String,
+    group_input: String,
+    import_path: String,
+    // 批量操作结果提示, 如导出路径
+    notice: Option<String>,
+    // 待确认添加的主播
+    draft: Option<Draft>,
+    // 历史页和日志页
+    history: History,
+    log_filter: String,
+    // 上次读取或保存的配置文件内容, 作为合并外部修改的基准
+    synced: Option<SavedState>,
+    disk_mtime: Option<SystemTime>,
+    // 正在进行的保存, 期间不检查外部修改
+    saving: usize,
+    // 保存期间读到了外部修改, 保存完成后重新读取
+    recheck_disk: bool,
+    reloading: bool,
+    reload_conflict: Option<ReloadConflict>,
+    // 已创建的命名配置, 打开设置页时更新
+    profiles: Vec<String>,
+    // 检测到的播放器, 以及配置的播放器能否执行
+    players: Vec<PlayerPreset>,
+    player_missing: bool,
+    // 按主播 key 记录打开的 mpv 窗口
+    mpv_sessions: HashMap<String, MpvSession>,
+    next_session: usize,
+    // 播放前等待重新查询的主播, 值为点击时的线路
+    pending_play: HashMap<String, model::Node>,
+}
+
+struct MpvSession {
+    // 同一主播关闭后重新打开时区分新旧窗口
+    id: usize,
+    socket: PathBuf,
+    state: Option<PlaybackState>,
+    // 等待上一次读取状态返回
+    polling: bool,
+    // 已经通过 IPC 连接成功, 之前窗口可能还在启动
+    ready: bool,
+}
+
+/// 配置文件的外部修改和程序中的修改冲突, 等待用户选择
+struct ReloadConflict {
+    theirs: SavedState,
+    text: String,
+}
+
+/// 添加主播前先查询一次, 用昵称填充名称, 确认后才加入列表
+pub struct Draft {
+    anchor: AnchorInfo,
+    // 用户手动改过名称后不再自动填充
+    name_edited: bool,
+}
+
+/// 全量刷新的进度
+pub struct RefreshProgress {
+    total: usize,
+    waiting: HashSet<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Loaded(SavedState),
-    Saved,
+    Saved(Option<SystemTime>),
+    DiskChanged(Result<SavedState, String>),
+    OnReloadConflict(bool),
+    OnProfile(Option<String>),
+    OnDetectPlayers,
+    PlayersDetected(Vec<PlayerPreset>),
+    ProfileLoaded(Option<String>, Result<SavedState, String>),
     SubmitAnchor(AnchorInfo),
     OnPlay(usize, model::Node),
+    MpvLoaded(usize, usize, model::Node, Result<(), String>),
+    MpvState(String, usize, Result<PlaybackState, String>),
+    OnPlayerControl(usize, PlayerControl),
+    PlayerExited(String, usize),
     OnItemUpdate(usize, AnchorItemUpdateType),
+    OnLiveLineSwitch(usize, i32),
+    OnDeleteConfirm(bool),
+    OnDraftName(String),
+    OnDraftConfirm(bool),
+    Undo,
+    OnSelectMode,
+    OnCheck(usize, bool),
+    OnSelectAll,
+    OnSelectFiltered,
+    OnClearSelection,
+    OnFilter(String),
+    OnGroupInput(String),
+    OnImportPath(String),
+    OnImport,
+    Imported(Result<Vec<AnchorInfo>, String>),
+    OnBulk(BulkAction),
+    Exported(Result<PathBuf, String>),
+    KeyPressed(KeyCode, Modifiers),
+    EscapeCaptured,
+    WindowResized(u32, u32),
+    WindowMoved(i32, i32),
+    WindowFocused,
+    CloseRequested,
+    Exit,
     OnFlush,
-    OnSetting,
+    OnPage(Page),
+    OnClearHistory,
+    OnLogFilter(String),
+    OnClearLogs,
     OnSettingUpdate(Option<AppConfig>),
+    OnCredentialsUpdate(Credentials),
+    OnCheckCookie(Platform, String),
+    CookieChecked(Platform, CookieStatus),
+    TaskStarted(String),
     TaskResult(AnchorInfo),
+    ImageLoaded(String, String, Result<Vec<u8>, String>),
+    Tick,
+    FastTick,
+    // 只用于重新生成界面, 如更新 "x分钟前"
+    Redraw,
     Ignore,
 }
 
+/// 对多选的主播执行的操作
+#[derive(Debug, Clone, Copy)]
+pub enum BulkAction {
+    Delete,
+    Refresh,
+    MoveToGroup,
+    Export,
+    PlayLive,
+}
+
 impl Application for SeamUI {
     type Executor = iced::executor::Default;
 
@@ -57,11 +230,20 @@ impl Application for SeamUI {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
-        let (result_sender, result_receiver) = mpsc::unbounded_channel::<AnchorInfo>();
-        let (task_sender, task_receiver) = mpsc::unbounded_channel::<(AnchorInfo, AppConfig)>();
+        let (result_sender, result_receiver) = mpsc::unbounded_channel::<TaskEvent>();
+        let (task_sender, task_receiver) = mpsc::unbounded_channel::<ServerTask>();
+        let server = SeamServer::new(result_sender, task_receiver);
+        let server_stats = server.stats();
+        let credentials = Credentials::load_blocking().unwrap_or_else(|e| {
+            log::error!("load credentials err {:?}", e);
+            Credentials::default()
+        });
+        task_sender
+            .send(ServerTask::Credentials(credentials.clone()))
+            .expect("send err");
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async { SeamServer::new(result_sender, task_receiver).run().await })
+            rt.block_on(async { server.run().await })
         });
 
         (
@@ -70,14 +252,49 @@ impl Application for SeamUI {
                 anchor_input_state: RefCell::new(AnchorInputState::default()),
                 anchor_list: vec![],
                 task_sender,
+                server_stats,
                 result_receiver: RefCell::new(Some(result_receiver)),
                 config: AppConfig::default(),
-                show_setting: false,
+                custom_palette: None,
+                system_dark: theme::system_dark(),
+                page: Page::default(),
+                cfg_panel_state: RefCell::new(CfgPanelState::default()),
+                selected: None,
+                pending_delete: None,
+                thumbnails: HashMap::new(),
+                refresh: None,
+                credentials,
+                cookie_status: HashMap::new(),
+                undo: UndoStack::default(),
+                select_mode: false,
+                checked: HashSet::new(),
+                filter: String::new(),
+                group_input: String::new(),
+                import_path: String::new(),
+                notice: None,
+                draft: None,
+                history: History::default(),
+                log_filter: String::new(),
+                synced: None,
+                disk_mtime: None,
+                saving: 0,
+                recheck_disk: false,
+                reloading: false,
+                reload_conflict: None,
+                profiles: profile::current().profiles(),
+                players: vec![],
+                player_missing: false,
+                mpv_sessions: HashMap::new(),
+                next_session: 0,
+                pending_play: HashMap::new(),
             },
-            Command::perform(SavedState::load(), |r| {
-                info!("load is ok {:?}", r.is_ok());
-                Message::Loaded(r.expect("load"))
-            }),
+            Command::batch([
+                Command::perform(SavedState::load(), |r| {
+                    info!("load is ok {:?}", r.is_ok());
+                    Message::Loaded(r.expect("load"))
+                }),
+                Command::perform(async { player::detect() }, Message::PlayersDetected),
+            ]),
         )
     }
 
@@ -89,109 +306,605 @@ impl Application for SeamUI {
         match message {
             Message::Loaded(s) => {
                 info!("load anchors len={}, cfg:{:?}", s.anchors.len(), s.config);
+                self.synced = Some(s.clone());
+                self.disk_mtime = SavedState::modified();
                 self.anchor_list = s.anchors;
                 self.config = s.config;
+                i18n::set_lang(self.config.lang);
+                self.reload_palette();
+                self.check_player();
                 self.loaded = true;
-                self.anchor_list.iter().for_each(|v| {
-                    self.task_sender
-                        .send((v.clone(), self.config.clone()))
-                        .expect("send err");
-                });
+                self.refresh_all();
 
-                Command::none()
+                Command::perform(self.image_cache().purge(), |v| {
+                    info!("purge image cache {:?}", v);
+                    Message::Ignore
+                })
             }
 
             Message::SubmitAnchor(anchor) => {
-                self.anchor_list.push(anchor.clone());
-                self.task_sender
-                    .send((anchor, self.config.clone()))
-                    .expect("send err");
-                let save = Command::perform(
-                    SavedState {
-                        anchors: self.anchor_list.clone(),
-                        config: self.config.clone(),
+                if self.draft.is_some() {
+                    let _ = self.update(Message::OnDraftConfirm(false));
+                }
+                self.query(&anchor);
+                self.draft = Some(Draft {
+                    anchor,
+                    name_edited: false,
+                });
+                Command::none()
+            }
+            Message::OnDraftName(name) => {
+                if let Some(d) = self.draft.as_mut() {
+                    d.anchor.name = name;
+                    d.name_edited = true;
+                }
+                Command::none()
+            }
+            Message::OnDraftConfirm(ok) => {
+                let Some(Draft { mut anchor, .. }) = self.draft.take() else {
+                    return Command::none();
+                };
+                if !ok {
+                    if !self.anchor_list.iter().any(|a| a.key() == anchor.key()) {
+                        self.task_sender
+                            .send(ServerTask::Cancel(anchor.key()))
+                            .expect("send err");
                     }
-                    .save(),
-                    |v| {
-                        info!("saved {:?}", v);
-                        Message::Saved
-                    },
-                );
-                Command::batch(vec![Command::none(), save])
+                    return Command::none();
+                }
+                if anchor.name.trim().is_empty() {
+                    anchor.name = anchor.url.clone().unwrap_or(anchor.room_id.clone());
+                }
+                // 查询还未返回时结果会按 key 更新到列表中
+                self.anchor_list.push(anchor);
+                self.save("add")
             }
 
             Message::OnPlay(i, node) => {
                 info!("play idx:{} {:?} {:?}", i, node, self.config);
-                Command::perform(PlayState::play(node, self.config.clone()), move |v| {
-                    info!("play idx:{} {:?}", i, v);
-                    Message::Ignore
-                })
+                let Some(a) = self.anchor_list.get(i) else {
+                    return Command::none();
+                };
+                // 结果返回后在 TaskResult 中播放
+                if self.stale(a) {
+                    info!("resolve {} again before play", a.key());
+                    self.pending_play.insert(a.key(), node);
+                    self.query(a);
+                    return Command::none();
+                }
+                self.play(i, node)
+            }
+            Message::MpvLoaded(i, id, node, r) => {
+                info!("mpv load idx:{} {:?}", i, r);
+                let session = self.mpv_sessions.iter_mut().find(|(_, s)| s.id == id);
+                match (r, session) {
+                    (Ok(_), Some((_, s))) => s.ready = true,
+                    (Ok(_), None) => {}
+                    // 窗口还在启动, 再打开一个会出现两个窗口
+                    (Err(e), Some((key, s))) if !s.ready => {
+                        log::warn!("mpv {} not ready: {}", key, e);
+                    }
+                    // 窗口已关闭, 打开新的播放器
+                    (Err(_), session) => {
+                        if let Some(key) = session.map(|(k, _)| k.clone()) {
+                            self.mpv_sessions.remove(&key);
+                        }
+                        return self.launch(i, node);
+                    }
+                }
+                Command::none()
+            }
+            // 主播修改后会话换到新的键下, 按会话编号查找
+            Message::MpvState(key, id, r) => {
+                let Some(s) = self.mpv_sessions.values_mut().find(|s| s.id == id) else {
+                    return Command::none();
+                };
+                s.polling = false;
+                match r {
+                    Ok(state) => {
+                        s.state = Some(state);
+                        s.ready = true;
+                    }
+                    // 窗口刚打开时 socket 可能还没有创建
+                    Err(e) => debug!("mpv state {} {}", key, e),
+                }
+                Command::none()
+            }
+            Message::OnPlayerControl(i, c) => {
+                let Some(s) = self
+                    .anchor_list
+                    .get(i)
+                    .and_then(|a| self.mpv_sessions.get(&a.key()))
+                else {
+                    return Command::none();
+                };
+                let key = self.anchor_list[i].key();
+                let (id, socket) = (s.id, s.socket.clone());
+                Command::perform(
+                    async move {
+                        mpv::control(socket.clone(), c).await?;
+                        mpv::state(socket).await
+                    },
+                    move |r| Message::MpvState(key, id, r.map_err(|e| e.to_string())),
+                )
+            }
+            Message::PlayerExited(key, id) => {
+                debug!("player {} exited", key);
+                self.mpv_sessions.retain(|_, s| s.id != id);
+                Command::none()
             }
             Message::OnItemUpdate(i, typ) => {
                 debug!("OnItemUpdate {} {:?}", i, typ);
                 match typ {
                     AnchorItemUpdateType::Del => {
-                        self.anchor_list.remove(i);
+                        let removed = self.remove_anchor(i);
+                        self.undo.push(Change::Delete(vec![(i, removed)]));
                     }
                     AnchorItemUpdateType::Update(n) => {
-                        *self.anchor_list.index_mut(i) = n;
+                        if let Some(old) = self.replace_anchor(i, n) {
+                            self.undo.push(Change::Update(vec![(i, old)]));
+                        }
                     }
                 }
-                Command::perform(
-                    SavedState {
-                        anchors: self.anchor_list.clone(),
-                        config: self.config.clone(),
-                    }
-                    .save(),
-                    |v| {
-                        info!("saved due to OnItemUpdate: {:?}", v);
-                        Message::Saved
-                    },
-                )
+                self.save("OnItemUpdate")
             }
 
+            Message::OnLiveLineSwitch(i, step) => {
+                let Some(a) = self.anchor_list.get_mut(i) else {
+                    return Command::none();
+                };
+                a.switch_line(step);
+                // 窗口打开时直接播放新的线路
+                let node = a.live_node().cloned();
+                match node {
+                    Some(node) if self.mpv_sessions.contains_key(&a.key()) => {
+                        self.update(Message::OnPlay(i, node))
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::OnDeleteConfirm(ok) => {
+                let Some(i) = self.pending_delete.take() else {
+                    return Command::none();
+                };
+                if !ok || i >= self.anchor_list.len() {
+                    return Command::none();
+                }
+                self.update(Message::OnItemUpdate(i, AnchorItemUpdateType::Del))
+            }
+            Message::Undo => {
+                let Some(change) = self.undo.pop() else {
+                    return Command::none();
+                };
+                let requery: Vec<usize> = match &change {
+                    Change::Update(v) => v
+                        .iter()
+                        .filter(|(i, a)| {
+                            matches!(self.anchor_list.get(*i), Some(c) if c.key() != a.key())
+                        })
+                        .map(|(i, _)| *i)
+                        .collect(),
+                    _ => vec![],
+                };
+                if let Change::Delete(v) = &change {
+                    v.iter().for_each(|(_, a)| self.query(a));
+                }
+                let imported: Vec<String> = match &change {
+                    Change::Import(start, n) => self
+                        .anchor_list
+                        .iter()
+                        .skip(*start)
+                        .take(*n)
+                        .map(AnchorInfo::key)
+                        .collect(),
+                    _ => vec![],
+                };
+                self.pending_delete = None;
+                self.checked.clear();
+                self.selected = change.revert(&mut self.anchor_list);
+                // 撤销导入和删除一样取消查询, 结果不会再触发 hook
+                for key in imported {
+                    if !self.anchor_list.iter().any(|a| a.key() == key) {
+                        self.forget(&key);
+                    }
+                }
+                for i in requery {
+                    self.anchor_list[i].clear_status();
+                    self.query(&self.anchor_list[i]);
+                }
+                self.save("Undo")
+            }
+            Message::OnSelectMode => {
+                self.select_mode = !self.select_mode;
+                self.checked.clear();
+                self.notice = None;
+                Command::none()
+            }
+            Message::OnCheck(i, checked) => {
+                if checked {
+                    self.checked.insert(i);
+                } else {
+                    self.checked.remove(&i);
+                }
+                Command::none()
+            }
+            Message::OnSelectAll => {
+                self.checked = (0..self.anchor_list.len()).collect();
+                Command::none()
+            }
+            Message::OnSelectFiltered => {
+                self.checked = self.visible().collect();
+                Command::none()
+            }
+            Message::OnClearSelection => {
+                self.checked.clear();
+                Command::none()
+            }
+            Message::OnFilter(s) => {
+                self.filter = s;
+                Command::none()
+            }
+            Message::OnGroupInput(s) => {
+                self.group_input = s;
+                Command::none()
+            }
+            Message::OnImportPath(s) => {
+                self.import_path = s;
+                Command::none()
+            }
+            Message::OnImport => {
+                let path = self.import_path.trim();
+                if path.is_empty() {
+                    return Command::none();
+                }
+                Command::perform(SavedState::import(path.into()), |r| {
+                    Message::Imported(r.map_err(|e| e.to_string()))
+                })
+            }
+            Message::Imported(Err(e)) => {
+                self.notice = Some(format!("{} {}", tr("item.error"), e));
+                Command::none()
+            }
+            Message::Imported(Ok(anchors)) => {
+                self.notice = Some(format!("{} {}", tr("bulk.imported"), anchors.len()));
+                if anchors.is_empty() {
+                    return Command::none();
+                }
+                self.undo
+                    .push(Change::Import(self.anchor_list.len(), anchors.len()));
+                anchors.iter().for_each(|a| self.query(a));
+                self.anchor_list.extend(anchors);
+                self.checked.clear();
+                self.save("import")
+            }
+            Message::OnBulk(action) => self.on_bulk(action),
+            Message::Exported(r) => {
+                self.notice = Some(match r {
+                    Ok(p) => format!("{} {}", tr("bulk.exported"), p.display()),
+                    Err(e) => format!("{} {}", tr("item.error"), e),
+                });
+                Command::none()
+            }
+            Message::KeyPressed(key, modifiers) => self.on_key(key, modifiers),
+            Message::EscapeCaptured if self.draft.is_some() => {
+                self.update(Message::OnDraftConfirm(false))
+            }
+            Message::WindowResized(width, height) => {
+                // iced 报告的大小已除以界面缩放, 启动时的窗口大小不含缩放
+                let scale = self.config.window.scale_factor;
+                self.config.window.width = (width as f64 * scale).round() as u32;
+                self.config.window.height = (height as f64 * scale).round() as u32;
+                Command::none()
+            }
+            // 移动事件和 `Position::Specific` 都是物理像素, 不受缩放影响
+            Message::WindowMoved(x, y) => {
+                self.config.window.x = Some(x);
+                self.config.window.y = Some(y);
+                Command::none()
+            }
+            Message::CloseRequested => {
+                if !self.loaded {
+                    return window::close();
+                }
+                Command::perform(self.saved_state().save(), |v| {
+                    info!("saved due to CloseRequested: {:?}", v);
+                    Message::Exit
+                })
+            }
+            Message::Exit => window::close(),
+            // 查询结果返回前保留上一次的状态
+            Message::TaskStarted(key) => {
+                let draft = self.draft.as_mut().map(|d| &mut d.anchor);
+                for v in self.anchor_list.iter_mut().chain(draft) {
+                    if v.key() == key {
+                        v.checking = true;
+                    }
+                }
+                Command::none()
+            }
             Message::TaskResult(info) => {
-                self.anchor_list
-                    .iter_mut()
-                    .filter(|m| {
-                        if m.platform == info.platform && m.room_id == info.room_id {
-                            return true;
+                if let Some(d) = self.draft.as_mut().filter(|d| d.anchor.key() == info.key()) {
+                    d.anchor.checking = false;
+                    d.anchor.show_type = info.show_type.clone();
+                    d.anchor.updated_at = info.updated_at;
+                    if let (false, Some(ShowType::On(s))) = (d.name_edited, &info.show_type) {
+                        if let Some(name) = s.suggested_name() {
+                            d.anchor.name = name.to_string();
                         }
-                        false
+                    }
+                }
+                let mut hooks = vec![];
+                for v in self
+                    .anchor_list
+                    .iter_mut()
+                    .filter(|m| m.key() == info.key())
+                {
+                    v.checking = false;
+                    let event =
+                        hooks::LiveEvent::changed(v.show_type.as_ref(), info.show_type.as_ref());
+                    v.show_type = info.show_type.clone();
+                    v.updated_at = info.updated_at;
+                    if let Some(event) = event {
+                        self.history
+                            .push(HistoryEntry::new(v, HistoryKind::Live(event)));
+                        let payload = hooks::HookPayload::new(event, v);
+                        hooks.extend(
+                            self.config
+                                .hooks
+                                .iter()
+                                .chain(&v.hooks)
+                                .filter(|h| h.applies_to(event))
+                                .map(|h| (h.clone(), payload.clone())),
+                        );
+                    }
+                }
+                self.refresh_done(&info.key());
+                let proxy = self.config.proxy.for_platform(None).map(str::to_string);
+                let mut commands: Vec<_> = hooks
+                    .into_iter()
+                    .map(|(hook, payload)| {
+                        let name = payload.name.clone();
+                        Command::perform(
+                            hooks::run(hook, payload, self.config.server.clone(), proxy.clone()),
+                            move |r| {
+                                info!("hook for {} {:?}", name, r);
+                                Message::Ignore
+                            },
+                        )
                     })
-                    .for_each(|v| {
-                        v.show_type = info.show_type.clone();
+                    .collect();
+                commands.push(self.load_thumbnail(&info));
+                if let Some(cached) = self.pending_play.remove(&info.key()) {
+                    commands.push(self.play_fresh(&info.key(), cached));
+                }
+                Command::batch(commands)
+            }
+            // 每次消息都会重新生成界面, 只在需要时才频繁触发
+            Message::Tick | Message::WindowFocused => {
+                self.refresh_system_theme();
+                self.check_disk()
+            }
+            // 读取 mpv 状态, 撤销提示条超时后随界面一起消失
+            Message::FastTick => self.poll_mpv(),
+            Message::Saved(mtime) => {
+                self.saving = self.saving.saturating_sub(1);
+                // 只记录自己写入后的修改时间, 之后的外部修改时间不同, 仍会读取合并
+                self.disk_mtime = self.disk_mtime.max(mtime);
+                if self.saving == 0 && std::mem::take(&mut self.recheck_disk) {
+                    self.disk_mtime = None;
+                    return self.check_disk();
+                }
+                Command::none()
+            }
+            Message::DiskChanged(Err(e)) => {
+                self.reloading = false;
+                log::warn!("reload {} err {}", SavedState::path().display(), e);
+                Command::none()
+            }
+            Message::DiskChanged(Ok(theirs)) => {
+                self.reloading = false;
+                if self.saving > 0 {
+                    // 读到的可能是写入前的内容, 不能以当前的基准合并
+                    self.recheck_disk = true;
+                    return Command::none();
+                }
+                let Some(base) = self.synced.replace(theirs.clone()) else {
+                    return Command::none();
+                };
+                let mut merged = reload::merge(&base, &self.saved_state(), &theirs);
+                // 设置页中还没保存的修改也算冲突
+                if self.cfg_panel_state.borrow().is_dirty(&self.config)
+                    && !reload::same_config(&base.config, &theirs.config)
+                {
+                    merged.config_conflict = true;
+                }
+                info!(
+                    "{} changed on disk, conflicts {:?} config {}",
+                    SavedState::path().display(),
+                    merged.conflicts,
+                    merged.config_conflict
+                );
+                let write_back = !reload::same_saved(&merged.state, &theirs);
+                if merged.has_conflict() {
+                    let mut names = merged.conflicts.clone();
+                    if merged.config_conflict {
+                        names.push(tr("reload.settings").to_string());
+                    }
+                    self.apply_state(merged.state);
+                    self.reload_conflict = Some(ReloadConflict {
+                        theirs,
+                        text: format!("{} {}", tr("reload.conflict"), names.join(", ")),
                     });
+                    return Command::none();
+                }
+                self.apply_state(merged.state);
+                if write_back {
+                    return self.save("reload merge");
+                }
                 Command::none()
             }
-            Message::OnFlush => {
-                self.anchor_list.iter().for_each(|v| {
+            Message::OnDetectPlayers => {
+                Command::perform(async { player::detect() }, Message::PlayersDetected)
+            }
+            Message::PlayersDetected(players) => {
+                info!("detected players {:?}", players);
+                self.players = players;
+                self.check_player();
+                Command::none()
+            }
+            Message::OnProfile(name) => {
+                let mut loc = profile::current();
+                if !self.loaded || loc.profile == name {
+                    return Command::none();
+                }
+                loc.profile = name.clone();
+                let save = self.save("switch profile");
+                // 读取完成前不检查外部修改
+                self.loaded = false;
+                let load = Command::perform(SavedState::load_from(loc.path()), move |r| {
+                    Message::ProfileLoaded(name, r.map_err(|e| e.to_string()))
+                });
+                Command::batch([save, load])
+            }
+            Message::ProfileLoaded(name, Err(e)) => {
+                self.loaded = true;
+                log::error!("load profile {:?} err {}", name, e);
+                Command::none()
+            }
+            Message::ProfileLoaded(name, Ok(mut s)) => {
+                if let Err(e) = profile::switch(name.clone()) {
+                    self.loaded = true;
+                    log::error!("switch profile err {}", e);
+                    return Command::none();
+                }
+                info!("switch to {}", SavedState::path().display());
+                let created = SavedState::modified().is_none();
+                for a in std::mem::take(&mut self.anchor_list) {
+                    self.refresh_done(&a.key());
                     self.task_sender
-                        .send((v.clone(), self.config.clone()))
+                        .send(ServerTask::Cancel(a.key()))
                         .expect("send err");
+                }
+                self.thumbnails.clear();
+                self.pending_play.clear();
+                self.selected = None;
+                self.pending_delete = None;
+                self.checked.clear();
+                self.undo = UndoStack::default();
+                self.reload_conflict = None;
+                *self.cfg_panel_state.borrow_mut() = CfgPanelState::default();
+                self.profiles = profile::current().profiles();
+                self.credentials = Credentials::load_blocking().unwrap_or_else(|e| {
+                    log::error!("load credentials err {:?}", e);
+                    Credentials::default()
                 });
+                self.cookie_status.clear();
+                self.task_sender
+                    .send(ServerTask::Credentials(self.credentials.clone()))
+                    .expect("send err");
+                // 窗口保持当前的位置和大小
+                s.config.window = self.config.window.clone();
+                let loaded = self.update(Message::Loaded(s));
+                if !created {
+                    return loaded;
+                }
+                // 新建的配置立即写入文件, 否则下次打开设置时不在配置列表中
+                if let Some(n) = name.filter(|n| !self.profiles.contains(n)) {
+                    self.profiles.push(n);
+                    self.profiles.sort();
+                }
+                Command::batch([loaded, self.save("create profile")])
+            }
+            Message::OnReloadConflict(keep_local) => {
+                let Some(c) = self.reload_conflict.take() else {
+                    return Command::none();
+                };
+                if keep_local {
+                    return self.save("keep local");
+                }
+                // 以程序中的内容为基准合并, 结果即磁盘上的内容
+                let ours = self.saved_state();
+                let merged = reload::merge(&ours, &ours, &c.theirs);
+                *self.cfg_panel_state.borrow_mut() = CfgPanelState::default();
+                self.apply_state(merged.state);
                 Command::none()
             }
-            Message::OnSetting => {
-                self.show_setting = true;
+            Message::ImageLoaded(key, url, r) => {
+                match r {
+                    // 缓存文件没有扩展名, 按内容判断格式
+                    Ok(data) => {
+                        if let Some((u, handle)) = self.thumbnails.get_mut(&key) {
+                            if *u == url {
+                                *handle = Some(image::Handle::from_memory(data));
+                            }
+                        }
+                    }
+                    Err(e) => log::warn!("load image {} err {}", url, e),
+                }
+                Command::none()
+            }
+            Message::OnFlush => {
+                self.refresh_all();
+                Command::none()
+            }
+            Message::OnPage(p) => {
+                if p == Page::Settings {
+                    self.profiles = profile::current().profiles();
+                }
+                self.page = p;
+                Command::none()
+            }
+            Message::OnClearHistory => {
+                self.history.clear();
+                Command::none()
+            }
+            Message::OnLogFilter(s) => {
+                self.log_filter = s;
+                Command::none()
+            }
+            Message::OnClearLogs => {
+                logs::clear();
+                Command::none()
+            }
+            Message::OnCredentialsUpdate(c) => {
+                if c == self.credentials {
+                    return Command::none();
+                }
+                self.credentials = c;
+                self.cookie_status.clear();
+                self.task_sender
+                    .send(ServerTask::Credentials(self.credentials.clone()))
+                    .expect("send err");
+                Command::perform(self.credentials.clone().save(), |v| {
+                    info!("saved credentials: {:?}", v.is_ok());
+                    Message::Ignore
+                })
+            }
+            Message::OnCheckCookie(p, cookie) => {
+                self.cookie_status.insert(p, CookieStatus::Checking);
+                let proxy = self.config.proxy.for_platform(Some(p)).map(str::to_string);
+                Command::perform(credentials::check(p, cookie, proxy), move |s| {
+                    Message::CookieChecked(p, s)
+                })
+            }
+            Message::CookieChecked(p, s) => {
+                self.cookie_status.insert(p, s);
                 Command::none()
             }
             Message::OnSettingUpdate(s) => {
-                self.show_setting = false;
-                if let Some(setting) = s {
+                self.page = Page::Anchors;
+                if let Some(mut setting) = s {
+                    // 窗口位置以当前为准, 设置面板只修改缩放
+                    setting.window = WindowConfig {
+                        scale_factor: setting.window.scale_factor,
+                        ..self.config.window.clone()
+                    };
                     self.config = setting;
+                    i18n::set_lang(self.config.lang);
+                    self.reload_palette();
+                    self.check_player();
                     log::info!("update setting {:?}", self.config);
-                    return Command::perform(
-                        SavedState {
-                            anchors: self.anchor_list.clone(),
-                            config: self.config.clone(),
-                        }
-                        .save(),
-                        |v| {
-                            info!("saved due to OnSettingUpdate: {:?}", v);
-                            Message::Saved
-                        },
-                    );
+                    return self.save("OnSettingUpdate");
                 }
                 Command::none()
             }
@@ -200,11 +913,24 @@ impl Application for SeamUI {
     }
 
     fn view(&self) -> iced::Element<'_, Self::Message, iced::Renderer<Self::Theme>> {
-        if self.show_setting {
-            self.setting_view()
-        } else {
-            self.main_page_view()
-        }
+        let page = match self.page {
+            Page::Anchors => self.main_page_view(),
+            Page::History => self.history_view(),
+            Page::Recordings => self.recordings_view(),
+            Page::Logs => self.logs_view(),
+            Page::Settings => self.setting_view(),
+        };
+        iced::widget::column![self.tab_bar(), page].into()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.config.window.scale_factor
+    }
+
+    fn theme(&self) -> Self::Theme {
+        self.config
+            .theme
+            .resolve(self.custom_palette.as_ref(), self.system_dark)
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
@@ -212,11 +938,573 @@ impl Application for SeamUI {
             "seam server",
             self.result_receiver.take(),
             move |mut r| async {
-                let result = r.as_mut().expect("get r").recv().await.expect("rev r");
-                (Message::TaskResult(result), r)
+                let event = r.as_mut().expect("get r").recv().await.expect("rev r");
+                let message = match event {
+                    TaskEvent::Started(key) => Message::TaskStarted(key),
+                    TaskEvent::Finished(info) => Message::TaskResult(info),
+                };
+                (message, r)
             },
         );
 
-        Subscription::batch([server])
+        let events = iced::subscription::events_with(|event, status| match (event, status) {
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code,
+                    modifiers,
+                }),
+                event::Status::Ignored,
+            ) => Some(Message::KeyPressed(key_code, modifiers)),
+            // 输入框获得焦点时 Esc 被捕获, 仍然用于取消添加主播
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: KeyCode::Escape,
+                    ..
+                }),
+                event::Status::Captured,
+            ) => Some(Message::EscapeCaptured),
+            (Event::Window(window::Event::Resized { width, height }), _) => {
+                Some(Message::WindowResized(width, height))
+            }
+            (Event::Window(window::Event::Moved { x, y }), _) => Some(Message::WindowMoved(x, y)),
+            (Event::Window(window::Event::CloseRequested), _) => Some(Message::CloseRequested),
+            (Event::Window(window::Event::Focused), _) => Some(Message::WindowFocused),
+            _ => None,
+        });
+
+        // 检查外部修改和系统主题
+        let tick = iced::time::every(Duration::from_secs(30)).map(|_| Message::Tick);
+        // 显示查询或记录时间的页面, 时间最多晚几秒更新
+        let ages = if matches!(self.page, Page::Anchors | Page::History | Page::Logs) {
+            iced::time::every(Duration::from_secs(5)).map(|_| Message::Redraw)
+        } else {
+            Subscription::none()
+        };
+        // 有打开的 mpv 窗口或撤销提示条时才每秒触发
+        let fast = if self.mpv_sessions.is_empty() && self.undo.bar(Instant::now()).is_none() {
+            Subscription::none()
+        } else {
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::FastTick)
+        };
+
+        Subscription::batch([server, events, tick, ages, fast])
+    }
+}
+
+/// 通过代理查询的子进程, 见 `server::child_query`, 不是子进程时返回 None
+pub fn child_query(args: &[String]) -> Option<anyhow::Result<()>> {
+    let (first, rest) = args.split_first()?;
+    (first == server::QUERY_ARG).then(|| server::child_query(rest))
+}
+
+/// 替代 `env_logger::init`, 同时把日志保存给日志页
+pub fn init_logger() {
+    logs::init();
+}
+
+/// 根据命令行参数和环境变量确定配置文件位置, 需要在读取配置前调用
+pub fn init_location(args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(PathBuf::from));
+    let loc = profile::resolve(
+        profile::parse_args(args)?,
+        |k| std::env::var(k).ok(),
+        exe_dir.as_deref(),
+    )?;
+    info!("config file {}", loc.path().display());
+    profile::init(loc);
+    Ok(())
+}
+
+pub fn window_config() -> WindowConfig {
+    match SavedState::load_blocking() {
+        Ok(s) => s.config.window,
+        Err(e) => {
+            info!("load window config {:?}", e);
+            WindowConfig::default()
+        }
+    }
+}
+
+impl SeamUI {
+    fn saved_state(&self) -> SavedState {
+        SavedState {
+            anchors: self.anchor_list.clone(),
+            config: self.config.clone(),
+        }
+    }
+
+    /// 保存到配置文件, 保存的内容作为之后合并外部修改的基准
+    fn save(&mut self, reason: &'static str) -> Command<Message> {
+        let state = self.saved_state();
+        self.synced = Some(state.clone());
+        self.saving += 1;
+        // 保存后磁盘上以程序中的内容为准
+        self.reload_conflict = None;
+        Command::perform(state.save(), move |v| {
+            info!("saved due to {}: {:?}", reason, v);
+            Message::Saved(v.ok())
+        })
+    }
+
+    /// 配置文件的修改时间变化时重新读取
+    fn check_disk(&mut self) -> Command<Message> {
+        if !self.loaded || self.saving > 0 || self.reloading {
+            return Command::none();
+        }
+        let modified = SavedState::modified();
+        if modified.is_none() || modified == self.disk_mtime {
+            return Command::none();
+        }
+        self.disk_mtime = modified;
+        self.reloading = true;
+        Command::perform(SavedState::read(), |r| {
+            Message::DiskChanged(r.map_err(|e| e.to_string()))
+        })
+    }
+
+    /// 使用从磁盘合并后的内容, 新增的主播会查询一次
+    fn apply_state(&mut self, s: SavedState) {
+        let old: HashSet<String> = self.anchor_list.iter().map(AnchorInfo::key).collect();
+        let new: HashSet<String> = s.anchors.iter().map(AnchorInfo::key).collect();
+        for key in old.difference(&new) {
+            self.forget(key);
+        }
+        let list_changed = self.anchor_list.len() != s.anchors.len()
+            || self
+                .anchor_list
+                .iter()
+                .zip(&s.anchors)
+                .any(|(a, b)| !a.same_saved(b));
+        if list_changed {
+            // 位置已变化, 选中项和撤销记录都不再有效
+            self.selected = None;
+            self.pending_delete = None;
+            self.checked.clear();
+            self.undo = UndoStack::default();
+        }
+        self.anchor_list = s.anchors;
+        for a in self.anchor_list.iter().filter(|a| !old.contains(&a.key())) {
+            self.query(a);
+        }
+        if s.config != self.config {
+            self.config = s.config;
+            i18n::set_lang(self.config.lang);
+            self.reload_palette();
+            self.check_player();
+            if !self.cfg_panel_state.borrow().is_dirty(&self.config) {
+                *self.cfg_panel_state.borrow_mut() = CfgPanelState::default();
+            }
+        }
+    }
+
+    fn image_cache(&self) -> ImageCache {
+        ImageCache::new(
+            ImageCache::default_dir(),
+            Duration::from_secs(self.config.image_cache_hours * 3600),
+        )
+    }
+
+    fn load_thumbnail(&mut self, info: &AnchorInfo) -> Command<Message> {
+        if self.config.compact_mode {
+            return Command::none();
+        }
+        let Some(ShowType::On(s)) = &info.show_type else {
+            return Command::none();
+        };
+        let Some(url) = s.thumbnail() else {
+            return Command::none();
+        };
+        let key = info.key();
+        if matches!(self.thumbnails.get(&key), Some((u, _)) if u == url) {
+            return Command::none();
+        }
+        let url = url.to_string();
+        self.thumbnails.insert(key.clone(), (url.clone(), None));
+        let cache = self
+            .image_cache()
+            .proxy(self.config.proxy.for_platform(info.platform));
+        Command::perform(cache.fetch(url.clone()), move |r| {
+            Message::ImageLoaded(key, url, r.map_err(|e| e.to_string()))
+        })
+    }
+
+    fn query(&self, anchor: &AnchorInfo) {
+        if !anchor.is_supported() {
+            return;
+        }
+        self.task_sender
+            .send(ServerTask::Query(anchor.clone(), self.config.clone()))
+            .expect("send err");
+    }
+
+    fn refresh_all(&mut self) {
+        self.refresh((0..self.anchor_list.len()).collect());
+    }
+
+    fn refresh(&mut self, indices: Vec<usize>) {
+        let anchors: Vec<_> = indices
+            .into_iter()
+            .filter_map(|i| self.anchor_list.get(i))
+            .filter(|a| a.is_supported())
+            .collect();
+        let waiting: HashSet<_> = anchors.iter().map(|a| a.key()).collect();
+        anchors.into_iter().for_each(|v| self.query(v));
+        self.refresh = if waiting.is_empty() {
+            None
+        } else {
+            Some(RefreshProgress {
+                total: waiting.len(),
+                waiting,
+            })
+        };
+    }
+
+    fn refresh_done(&mut self, key: &str) {
+        if let Some(r) = self.refresh.as_mut() {
+            r.waiting.remove(key);
+            if r.waiting.is_empty() {
+                self.refresh = None;
+            }
+        }
+    }
+
+    // 平台或房间号改变时取消旧查询并重新查询, 名称分组等保存在 AnchorInfo 中的数据不变
+    fn replace_anchor(&mut self, i: usize, mut n: AnchorInfo) -> Option<AnchorInfo> {
+        let old_key = self.anchor_list.get(i)?.key();
+        let rekeyed = old_key != n.key();
+        if rekeyed {
+            n.clear_status();
+        }
+        let old = std::mem::replace(self.anchor_list.index_mut(i), n);
+        if rekeyed {
+            if !self.anchor_list.iter().any(|a| a.key() == old_key) {
+                self.refresh_done(&old_key);
+                self.thumbnails.remove(&old_key);
+                // 已打开的窗口和等待播放的线路跟随主播换到新的键
+                let new_key = self.anchor_list[i].key();
+                if let Some(s) = self.mpv_sessions.remove(&old_key) {
+                    self.mpv_sessions.entry(new_key.clone()).or_insert(s);
+                }
+                if let Some(p) = self.pending_play.remove(&old_key) {
+                    self.pending_play.entry(new_key).or_insert(p);
+                }
+                self.task_sender
+                    .send(ServerTask::Cancel(old_key))
+                    .expect("send err");
+            }
+            self.query(&self.anchor_list[i]);
+        }
+        Some(old)
+    }
+
+    fn remove_anchor(&mut self, i: usize) -> AnchorInfo {
+        let removed = self.anchor_list.remove(i);
+        self.checked.clear();
+        let key = removed.key();
+        if !self.anchor_list.iter().any(|a| a.key() == key) {
+            self.forget(&key);
+        }
+        self.selected = match self.selected {
+            _ if self.anchor_list.is_empty() => None,
+            Some(s) if s > i || s == self.anchor_list.len() => Some(s - 1),
+            s => s,
+        };
+        // 待确认删除的序号跟随列表移动, 删除的正是它时取消确认
+        self.pending_delete = match self.pending_delete {
+            Some(p) if p == i => None,
+            Some(p) if p > i => Some(p - 1),
+            p => p,
+        };
+        removed
+    }
+
+    /// 列表中已没有该 key 的主播, 取消查询并丢弃封面和待播放的线路
+    fn forget(&mut self, key: &str) {
+        self.refresh_done(key);
+        self.thumbnails.remove(key);
+        self.pending_play.remove(key);
+        self.task_sender
+            .send(ServerTask::Cancel(key.to_string()))
+            .expect("send err");
+    }
+
+    /// 符合筛选条件的主播
+    fn visible(&self) -> impl Iterator<Item = usize> + '_ {
+        self.anchor_list
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| self.filter.trim().is_empty() || a.matches(&self.filter))
+            .map(|(i, _)| i)
+    }
+
+    // 批量修改只产生一次撤销记录和一次保存
+    fn on_bulk(&mut self, action: BulkAction) -> Command<Message> {
+        let mut indices: Vec<usize> = self
+            .checked
+            .iter()
+            .copied()
+            .filter(|i| *i < self.anchor_list.len())
+            .collect();
+        indices.sort_unstable();
+        if indices.is_empty() {
+            return Command::none();
+        }
+        match action {
+            BulkAction::Delete => {
+                let mut removed: Vec<_> = indices
+                    .into_iter()
+                    .rev()
+                    .map(|i| (i, self.remove_anchor(i)))
+                    .collect();
+                removed.reverse();
+                self.undo.push(Change::Delete(removed));
+                self.pending_delete = None;
+            }
+            BulkAction::MoveToGroup => {
+                let group = self.group_input.trim().to_string();
+                let old = indices
+                    .into_iter()
+                    .map(|i| {
+                        let a = &mut self.anchor_list[i];
+                        let old = a.clone();
+                        a.group = group.clone();
+                        (i, old)
+                    })
+                    .collect();
+                self.undo.push(Change::Update(old));
+            }
+            BulkAction::Refresh => {
+                self.refresh(indices);
+                return Command::none();
+            }
+            BulkAction::Export => {
+                let anchors = indices
+                    .into_iter()
+                    .map(|i| self.anchor_list[i].clone())
+                    .collect();
+                return Command::perform(SavedState::export(anchors), |r| {
+                    Message::Exported(r.map_err(|e| e.to_string()))
+                });
+            }
+            BulkAction::PlayLive => {
+                let plays = indices
+                    .into_iter()
+                    .filter_map(|i| Some((i, self.anchor_list[i].live_node()?.clone())))
+                    .collect::<Vec<_>>();
+                return Command::batch(
+                    plays
+                        .into_iter()
+                        .map(|(i, node)| self.update(Message::OnPlay(i, node))),
+                );
+            }
+        }
+        self.save("bulk action")
+    }
+
+    fn move_selected(&mut self, step: i32) -> Command<Message> {
+        let Some(from) = self.selected else {
+            return Command::none();
+        };
+        let to = from as i64 + step as i64;
+        if to < 0 || to >= self.anchor_list.len() as i64 {
+            return Command::none();
+        }
+        let to = to as usize;
+        let a = self.anchor_list.remove(from);
+        self.anchor_list.insert(to, a);
+        self.selected = Some(to);
+        self.checked.clear();
+        self.undo.push(Change::Move(from, to));
+        self.save("move")
+    }
+
+    fn on_key(&mut self, key: KeyCode, modifiers: Modifiers) -> Command<Message> {
+        if self.draft.is_some() && key == KeyCode::Escape {
+            return self.update(Message::OnDraftConfirm(false));
+        }
+        if self.pending_delete.is_some() {
+            return match key {
+                KeyCode::Enter | KeyCode::Y => self.update(Message::OnDeleteConfirm(true)),
+                KeyCode::Escape | KeyCode::N => self.update(Message::OnDeleteConfirm(false)),
+                _ => Command::none(),
+            };
+        }
+        let Some(action) = self.config.keybindings.action(key, modifiers) else {
+            return Command::none();
+        };
+        debug!("key action {:?}", action);
+        if action == KeyAction::OpenSetting {
+            return self.update(Message::OnPage(Page::Settings));
+        }
+        if self.page != Page::Anchors {
+            return Command::none();
+        }
+        let len = self.anchor_list.len();
+        match action {
+            KeyAction::Refresh => return self.update(Message::OnFlush),
+            KeyAction::FocusInput => return text_input::focus(anchor_input::input_id()),
+            KeyAction::SelectNext if len > 0 => {
+                self.selected = Some(self.selected.map_or(0, |s| (s + 1).min(len - 1)));
+            }
+            KeyAction::SelectPrev if len > 0 => {
+                self.selected = Some(self.selected.map_or(0, |s| s.saturating_sub(1)));
+            }
+            KeyAction::Play => {
+                if let Some(i) = self.selected {
+                    if let Some(node) = self.anchor_list.get(i).and_then(|a| a.live_node()) {
+                        return self.update(Message::OnPlay(i, node.clone()));
+                    }
+                }
+            }
+            KeyAction::LineNext | KeyAction::LinePrev => {
+                if let Some(i) = self.selected {
+                    let step = if action == KeyAction::LineNext { 1 } else { -1 };
+                    return self.update(Message::OnLiveLineSwitch(i, step));
+                }
+            }
+            KeyAction::Delete => self.pending_delete = self.selected,
+            KeyAction::MoveUp => return self.move_selected(-1),
+            KeyAction::MoveDown => return self.move_selected(1),
+            KeyAction::Undo => return self.update(Message::Undo),
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// 查询结果超过 `stream_ttl_secs` 时播放前需要重新查询, 直链不会过期
+    fn stale(&self, a: &AnchorInfo) -> bool {
+        let ttl = self.config.server.stream_ttl_secs;
+        if ttl == 0 || a.url.is_some() || !a.is_supported() {
+            return false;
+        }
+        !a.updated_at
+            .is_some_and(|t| t.elapsed().unwrap_or_default() < Duration::from_secs(ttl))
+    }
+
+    /// 播放重新查询后对应的线路, 没有时仍播放之前的地址
+    fn play_fresh(&mut self, key: &str, cached: model::Node) -> Command<Message> {
+        let Some(i) = self.anchor_list.iter().position(|a| a.key() == key) else {
+            return Command::none();
+        };
+        let a = &mut self.anchor_list[i];
+        let line = match &a.show_type {
+            Some(ShowType::On(s)) => s.equivalent_line(&cached, a.live_line),
+            _ => None,
+        };
+        let node = match line {
+            Some(l) => {
+                a.live_line = l;
+                a.live_node().cloned()
+            }
+            None => None,
+        };
+        let node = node.unwrap_or_else(|| {
+            log::warn!("no fresh line for {}, play cached {}", key, cached.url);
+            cached
+        });
+        self.play(i, node)
+    }
+
+    fn play(&mut self, i: usize, node: model::Node) -> Command<Message> {
+        let Some(a) = self.anchor_list.get(i) else {
+            return Command::none();
+        };
+        let kind = HistoryKind::Played(node.format.clone());
+        self.history.push(HistoryEntry::new(a, kind));
+        // 已有窗口时在窗口中切换, 失败再打开新的播放器
+        if let Some(s) = self.mpv_sessions.get(&a.key()) {
+            let (id, ready, socket, url) = (s.id, s.ready, s.socket.clone(), node.url.clone());
+            return Command::perform(
+                async move {
+                    if !ready {
+                        mpv::wait_ready(&socket).await?;
+                    }
+                    mpv::load(socket, url).await
+                },
+                move |r| Message::MpvLoaded(i, id, node, r.map_err(|e| e.to_string())),
+            );
+        }
+        self.launch(i, node)
+    }
+
+    /// 打开新的播放器, mpv 会带上 IPC 参数
+    fn launch(&mut self, i: usize, node: model::Node) -> Command<Message> {
+        let Some(a) = self.anchor_list.get(i) else {
+            return Command::none();
+        };
+        let key = a.key();
+        let platform = a.platform;
+        let mut ipc = None;
+        if self.config.mpv_ipc && player::is_mpv(&self.config.player_path) {
+            let socket = mpv::socket_path(&key);
+            self.next_session += 1;
+            self.mpv_sessions.insert(
+                key.clone(),
+                MpvSession {
+                    id: self.next_session,
+                    socket: socket.clone(),
+                    state: None,
+                    polling: false,
+                    ready: false,
+                },
+            );
+            ipc = Some(socket);
+        }
+        let id = self.next_session;
+        Command::perform(
+            PlayState::play(node, self.config.clone(), platform, ipc),
+            move |v| {
+                info!("play {} {:?}", key, v);
+                Message::PlayerExited(key, id)
+            },
+        )
+    }
+
+    /// 每秒读取打开的 mpv 窗口的播放状态
+    fn poll_mpv(&mut self) -> Command<Message> {
+        let commands: Vec<_> = self
+            .mpv_sessions
+            .iter_mut()
+            .filter(|(_, s)| !s.polling)
+            .map(|(key, s)| {
+                s.polling = true;
+                let (key, id) = (key.clone(), s.id);
+                Command::perform(mpv::state(s.socket.clone()), move |r| {
+                    Message::MpvState(key, id, r.map_err(|e| e.to_string()))
+                })
+            })
+            .collect();
+        Command::batch(commands)
+    }
+
+    fn check_player(&mut self) {
+        self.player_missing = player::find_executable(&self.config.player_path).is_none();
+        if self.player_missing {
+            log::warn!("player {} is not executable", self.config.player_path);
+        }
+    }
+
+    fn refresh_system_theme(&mut self) {
+        if self.config.theme == ThemeMode::System {
+            self.system_dark = theme::system_dark();
+        }
+    }
+
+    fn reload_palette(&mut self) {
+        self.refresh_system_theme();
+        self.custom_palette = None;
+        if self.config.theme != ThemeMode::Custom || self.config.custom_theme_path.is_empty() {
+            return;
+        }
+        match theme::load_palette(&self.config.custom_theme_path) {
+            Ok(p) => self.custom_palette = Some(p),
+            Err(e) => log::error!(
+                "load custom theme {} err {:?}",
+                self.config.custom_theme_path,
+                e
+            ),
+        }
     }
 }
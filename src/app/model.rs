@@ -1,16 +1,99 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    time::SystemTime,
+};
+
 use seam_core::live::Live;
 use serde::{Deserialize, Serialize};
 
+use super::{hooks::HookConfig, i18n::tr};
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
+#[serde(from = "SavedAnchor", into = "SavedAnchor")]
 pub struct AnchorInfo {
     pub name: String,
     pub platform: Option<Platform>,
     pub room_id: String,
-    #[serde(skip)]
+    // 直链主播, 不属于任何平台, 通过探测地址判断是否在线
+    pub url: Option<String>,
+    // 无法识别的平台名, 原样保存, 不查询
+    pub unsupported_platform: Option<String>,
+    // 分组名, 空字符串表示未分组
+    pub group: String,
+    pub notes: String,
+    // 自定义字段, 如真名、直播时间、语言
+    pub metadata: BTreeMap<String, String>,
+    // 只对该主播生效的 hook, 和全局 hook 一起执行
+    pub hooks: Vec<HookConfig>,
     pub show_type: Option<ShowType>,
+    pub live_line: usize,
+    // 正在查询, 查询结果返回前保留上一次的状态
+    pub checking: bool,
+    pub updated_at: Option<SystemTime>,
+}
+
+// AnchorInfo 在配置文件中的格式, 平台按字符串读取, 未知平台不会导致整个文件加载失败
+#[derive(Deserialize, Serialize)]
+struct SavedAnchor {
+    name: String,
+    platform: Option<String>,
+    room_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    group: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hooks: Vec<HookConfig>,
 }
 
-#[derive(Debug, strum::EnumIter, Clone, Deserialize, Serialize, Copy, PartialEq)]
+impl From<SavedAnchor> for AnchorInfo {
+    fn from(v: SavedAnchor) -> Self {
+        let (platform, unsupported_platform) = match v.platform.map(|p| p.parse::<Platform>()) {
+            Some(Ok(p)) => (Some(p), None),
+            Some(Err(e)) => {
+                log::warn!("anchor {}: {}", v.name, e);
+                (None, Some(e.0))
+            }
+            None => (None, None),
+        };
+        AnchorInfo {
+            name: v.name,
+            platform,
+            room_id: v.room_id,
+            url: v.url,
+            unsupported_platform,
+            group: v.group,
+            notes: v.notes,
+            metadata: v.metadata,
+            hooks: v.hooks,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<AnchorInfo> for SavedAnchor {
+    fn from(v: AnchorInfo) -> Self {
+        SavedAnchor {
+            name: v.name,
+            platform: v
+                .unsupported_platform
+                .or_else(|| v.platform.map(|p| format!("{:?}", p))),
+            room_id: v.room_id,
+            url: v.url,
+            group: v.group,
+            notes: v.notes,
+            metadata: v.metadata,
+            hooks: v.hooks,
+        }
+    }
+}
+
+#[derive(Debug, strum::EnumIter, Clone, Deserialize, Serialize, Copy, PartialEq, Eq, Hash)]
 pub enum Platform {
     BiliBili,
     DouYu,
@@ -23,23 +106,45 @@ pub enum Platform {
     Afreeca,
 }
 
-impl From<&str> for Platform {
-    fn from(value: &str) -> Self {
+/// 无法识别的平台名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPlatform(pub String);
+
+impl std::fmt::Display for UnknownPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown platform {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPlatform {}
+
+impl FromStr for Platform {
+    type Err = UnknownPlatform;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         match value {
-            "BiliBili" => Self::BiliBili,
-            "DouYu" => Self::DouYu,
-            "DouYin" => Self::DouYin,
-            "HuYa" => Self::HuYa,
-            "KuaiShou" => Self::KuaiShou,
-            "CC" => Self::CC,
-            "HuaJiao" => Self::HuaJiao,
-            "Now" => Self::Now,
-            "Afreeca" => Self::Afreeca,
-            _ => Self::BiliBili,
+            "BiliBili" => Ok(Self::BiliBili),
+            "DouYu" => Ok(Self::DouYu),
+            "DouYin" => Ok(Self::DouYin),
+            "HuYa" => Ok(Self::HuYa),
+            "KuaiShou" => Ok(Self::KuaiShou),
+            "CC" => Ok(Self::CC),
+            "HuaJiao" => Ok(Self::HuaJiao),
+            "Now" => Ok(Self::Now),
+            "Afreeca" => Ok(Self::Afreeca),
+            _ => Err(UnknownPlatform(value.to_string())),
         }
     }
 }
 
+impl TryFrom<&str> for Platform {
+    type Error = UnknownPlatform;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 impl Platform {
     pub fn as_seam_arg(&self) -> &'static str {
         match self {
@@ -54,42 +159,68 @@ impl Platform {
             Platform::Afreeca => "afreeca",
         }
     }
+    // 直播间网页地址, 给 streamlink/yt-dlp 这类按 url 解析的工具使用
+    pub fn room_url(&self, room_id: &str) -> String {
+        match self {
+            Platform::BiliBili => format!("https://live.bilibili.com/{}", room_id),
+            Platform::DouYu => format!("https://www.douyu.com/{}", room_id),
+            Platform::DouYin => format!("https://live.douyin.com/{}", room_id),
+            Platform::HuYa => format!("https://www.huya.com/{}", room_id),
+            Platform::KuaiShou => format!("https://live.kuaishou.com/u/{}", room_id),
+            Platform::CC => format!("https://cc.163.com/{}", room_id),
+            Platform::HuaJiao => format!("https://www.huajiao.com/l/{}", room_id),
+            Platform::Now => format!("https://now.qq.com/pcweb/story.html?roomid={}", room_id),
+            Platform::Afreeca => format!("https://play.afreecatv.com/{}", room_id),
+        }
+    }
     pub fn as_ui_text(&self) -> &'static str {
         match self {
-            Platform::BiliBili => "B站",
-            Platform::DouYu => "斗鱼",
-            Platform::DouYin => "抖音",
-            Platform::HuYa => "虎牙",
-            Platform::KuaiShou => "快手",
-            Platform::CC => "CC",
-            Platform::HuaJiao => "花椒",
-            Platform::Now => "Now",
-            Platform::Afreeca => "Afreeca",
+            Platform::BiliBili => tr("platform.bili"),
+            Platform::DouYu => tr("platform.douyu"),
+            Platform::DouYin => tr("platform.douyin"),
+            Platform::HuYa => tr("platform.huya"),
+            Platform::KuaiShou => tr("platform.kuaishou"),
+            Platform::CC => tr("platform.cc"),
+            Platform::HuaJiao => tr("platform.huajiao"),
+            Platform::Now => tr("platform.now"),
+            Platform::Afreeca => tr("platform.afreeca"),
         }
     }
-    pub async fn get_live(&self, room_id: &str) -> seam_core::error::Result<seam_core::live::Node> {
+    /// `cookie` 为该平台的登录 cookie, 以请求头的形式传给 seam_core
+    pub async fn get_live(
+        &self,
+        room_id: &str,
+        cookie: Option<&str>,
+    ) -> seam_core::error::Result<seam_core::live::Node> {
+        let h = cookie.map(|c| HashMap::from([("cookie".to_string(), c.to_string())]));
         match self {
-            Platform::BiliBili => seam_core::live::bili::Client {}.get(room_id, None).await,
-            Platform::DouYu => seam_core::live::douyu::Client {}.get(room_id, None).await,
-            Platform::DouYin => seam_core::live::douyin::Client {}.get(room_id, None).await,
-            Platform::HuYa => seam_core::live::huya::Client {}.get(room_id, None).await,
-            Platform::KuaiShou => seam_core::live::ks::Client {}.get(room_id, None).await,
-            Platform::CC => seam_core::live::cc::Client {}.get(room_id, None).await,
+            Platform::BiliBili => seam_core::live::bili::Client {}.get(room_id, h).await,
+            Platform::DouYu => seam_core::live::douyu::Client {}.get(room_id, h).await,
+            Platform::DouYin => seam_core::live::douyin::Client {}.get(room_id, h).await,
+            Platform::HuYa => seam_core::live::huya::Client {}.get(room_id, h).await,
+            Platform::KuaiShou => seam_core::live::ks::Client {}.get(room_id, h).await,
+            Platform::CC => seam_core::live::cc::Client {}.get(room_id, h).await,
             Platform::HuaJiao => {
                 seam_core::live::huajiao::Client {}
-                    .get(room_id, None)
+                    .get(room_id, h)
                     .await
             }
-            Platform::Now => seam_core::live::now::Client {}.get(room_id, None).await,
+            Platform::Now => seam_core::live::now::Client {}.get(room_id, h).await,
             Platform::Afreeca => {
                 seam_core::live::afreeca::Client {}
-                    .get(room_id, None)
+                    .get(room_id, h)
                     .await
             }
         }
     }
 }
 
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ui_text())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ShowType {
     // 开播
@@ -100,11 +231,116 @@ pub enum ShowType {
     Error(String),
 }
 
+impl AnchorInfo {
+    pub fn key(&self) -> String {
+        match &self.url {
+            Some(url) => format!("Url:{}", url),
+            None => format!("{:?}:{}", self.platform, self.room_id),
+        }
+    }
+
+    pub fn source_text(&self) -> &str {
+        if let Some(p) = &self.unsupported_platform {
+            return p;
+        }
+        match (&self.url, &self.platform) {
+            (Some(_), _) | (None, None) => tr("platform.url"),
+            (None, Some(p)) => p.as_ui_text(),
+        }
+    }
+
+    /// 名称、房间号、分组、备注或自定义字段中包含 `q`, 不区分大小写
+    pub fn matches(&self, q: &str) -> bool {
+        let q = q.trim().to_lowercase();
+        [&self.name, &self.room_id, &self.group, &self.notes]
+            .into_iter()
+            .chain(&self.url)
+            .chain(self.metadata.iter().flat_map(|(k, v)| [k, v]))
+            .any(|s| s.to_lowercase().contains(&q))
+    }
+
+    /// 保存到配置文件的内容是否相同
+    pub fn same_saved(&self, other: &AnchorInfo) -> bool {
+        self.name == other.name
+            && self.key() == other.key()
+            && self.unsupported_platform == other.unsupported_platform
+            && self.group == other.group
+            && self.notes == other.notes
+            && self.metadata == other.metadata
+            && self.hooks == other.hooks
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.unsupported_platform.is_none()
+    }
+
+    /// 清除查询结果, 平台或房间号改变后使用
+    pub fn clear_status(&mut self) {
+        self.show_type = None;
+        self.live_line = 0;
+        self.checking = false;
+        self.updated_at = None;
+    }
+
+    pub fn live_node(&self) -> Option<&Node> {
+        match &self.show_type {
+            Some(ShowType::On(s)) => s.nodes.as_ref()?.get(self.live_line),
+            _ => None,
+        }
+    }
+
+    pub fn switch_line(&mut self, step: i32) {
+        if let Some(ShowType::On(s)) = &self.show_type {
+            if let Some(n) = &s.nodes {
+                if !n.is_empty() {
+                    if step > 0 {
+                        self.live_line = (self.live_line + 1) % n.len();
+                    } else {
+                        self.live_line = (self.live_line + n.len() - 1) % n.len();
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SeamInfo {
     #[serde(default)]
     pub title: String,
     pub nodes: Option<Vec<Node>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    // 主播昵称
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "anchor")]
+    pub nickname: Option<String>,
+}
+
+impl SeamInfo {
+    /// 优先使用封面, 没有时使用头像
+    pub fn thumbnail(&self) -> Option<&str> {
+        self.cover.as_deref().or(self.avatar.as_deref())
+    }
+
+    /// 添加主播时建议的名称, 优先使用昵称, 没有时使用直播间标题
+    pub fn suggested_name(&self) -> Option<&str> {
+        self.nickname
+            .as_deref()
+            .or(Some(self.title.as_str()))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    }
+
+    /// 重新查询后与之前播放的 `node` 对应的线路: 同一序号且格式相同, 否则第一个格式相同的
+    pub fn equivalent_line(&self, node: &Node, line: usize) -> Option<usize> {
+        let nodes = self.nodes.as_ref()?;
+        if nodes.get(line).is_some_and(|n| n.format == node.format) {
+            return Some(line);
+        }
+        nodes.iter().position(|n| n.format == node.format)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -116,7 +352,7 @@ pub struct Node {
 #[cfg(test)]
 mod tests {
 
-    use super::SeamInfo;
+    use super::{AnchorInfo, Node, Platform, SeamInfo, UnknownPlatform};
 
     #[test]
     fn test_encode_decode() {
@@ -136,4 +372,93 @@ mod tests {
         println!("{:?}", info);
         println!("{}", serde_json::to_string(&info).unwrap());
     }
+
+    #[test]
+    fn test_parse_platform() {
+        assert_eq!("HuYa".parse::<Platform>(), Ok(Platform::HuYa));
+        assert_eq!(Platform::try_from("CC"), Ok(Platform::CC));
+        assert_eq!(
+            "Bilibili".parse::<Platform>(),
+            Err(UnknownPlatform("Bilibili".into()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_platform_round_trip() {
+        let list: Vec<AnchorInfo> = serde_json::from_str(
+            r#"[
+                {"name": "a", "platform": "DouYu", "room_id": "1"},
+                {"name": "b", "platform": "Twitch", "room_id": "2"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(list[0].platform, Some(Platform::DouYu));
+        assert!(list[0].is_supported());
+        assert_eq!(list[1].platform, None);
+        assert!(!list[1].is_supported());
+        assert_eq!(list[1].source_text(), "Twitch");
+
+        let saved = serde_json::to_value(&list).unwrap();
+        assert_eq!(saved[1]["platform"], "Twitch");
+        assert_eq!(saved[1]["room_id"], "2");
+    }
+
+    #[test]
+    fn test_matches() {
+        let a = AnchorInfo {
+            name: "Alice".into(),
+            room_id: "123".into(),
+            group: "游戏".into(),
+            ..Default::default()
+        };
+        assert!(a.matches("alice"));
+        assert!(a.matches(" 12 "));
+        assert!(a.matches("游戏"));
+        assert!(!a.matches("bob"));
+
+        let mut a = a;
+        a.notes = "weekend only".into();
+        a.metadata.insert("lang".into(), "English".into());
+        assert!(a.matches("WEEKEND"));
+        assert!(a.matches("lang"));
+        assert!(a.matches("english"));
+
+        let saved = serde_json::to_value(&a).unwrap();
+        assert_eq!(saved["group"], "游戏");
+        assert_eq!(saved["metadata"]["lang"], "English");
+        let b: AnchorInfo = serde_json::from_value(saved).unwrap();
+        assert!(b.same_saved(&a));
+    }
+
+    #[test]
+    fn test_suggested_name() {
+        let mut info: SeamInfo =
+            serde_json::from_str(r#"{"title": " 标题 ", "nodes": [], "anchor": "主播"}"#).unwrap();
+        assert_eq!(info.suggested_name(), Some("主播"));
+        info.nickname = None;
+        assert_eq!(info.suggested_name(), Some("标题"));
+        info.title.clear();
+        assert_eq!(info.suggested_name(), None);
+    }
+
+    #[test]
+    fn test_equivalent_line() {
+        let info: SeamInfo = serde_json::from_str(
+            r#"{"nodes": [
+                {"format": "flv", "url": "a?t=2"},
+                {"format": "m3u", "url": "b?t=2"},
+                {"format": "flv", "url": "c?t=2"}
+            ]}"#,
+        )
+        .unwrap();
+        let node = |format: &str| Node {
+            format: format.into(),
+            url: "x?t=1".into(),
+        };
+        assert_eq!(info.equivalent_line(&node("flv"), 2), Some(2));
+        // 序号对应的格式变了
+        assert_eq!(info.equivalent_line(&node("m3u"), 2), Some(1));
+        assert_eq!(info.equivalent_line(&node("flv"), 5), Some(0));
+        assert_eq!(info.equivalent_line(&node("rtmp"), 0), None);
+    }
 }
@@ -0,0 +1,290 @@
+//! 通过 mpv 的 JSON IPC 控制已打开的播放窗口, 切换线路时不用重新打开播放器
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use super::{image_cache::fnv1a, profile};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+// 等待刚打开的窗口创建 socket 的最长时间
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const VOLUME_STEP: f64 = 10.0;
+
+/// 从 mpv 读取的播放状态, 显示在主播条目中
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaybackState {
+    // 等待缓存时暂停
+    pub buffering: bool,
+    // 部分视频输出不支持统计掉帧
+    pub dropped_frames: Option<u64>,
+    pub volume: f64,
+    pub mute: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerControl {
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+}
+
+/// 每个主播的播放窗口使用单独的 socket, 名称带上进程号和配置文件路径,
+/// 多个实例或配置同时播放同一主播时不会连到别人的窗口
+pub fn socket_path(key: &str) -> PathBuf {
+    let scope = format!("{}\n{}", profile::current().path().display(), key);
+    let name = format!(
+        "seamui-mpv-{}-{:016x}",
+        std::process::id(),
+        fnv1a(scope.as_bytes())
+    );
+    if cfg!(windows) {
+        return PathBuf::from(format!(r"\\.\pipe\{}", name));
+    }
+    // 优先放在只有当前用户能访问的运行时目录
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|d| d.is_dir())
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("{}.sock", name))
+}
+
+/// 启动 mpv 时加上的参数
+pub fn ipc_arg(socket: &Path) -> String {
+    format!("--input-ipc-server={}", socket.display())
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    request_id: Option<usize>,
+    error: Option<String>,
+    #[serde(default)]
+    data: Value,
+}
+
+/// 在一个连接上依次发送命令, 按 request_id 返回各自的结果, 跳过 mpv 推送的事件
+async fn requests<S: AsyncRead + AsyncWrite>(
+    stream: S,
+    commands: &[Value],
+) -> anyhow::Result<Vec<anyhow::Result<Value>>> {
+    let (r, mut w) = tokio::io::split(stream);
+    let mut data = vec![];
+    for (i, c) in commands.iter().enumerate() {
+        serde_json::to_writer(&mut data, &json!({ "command": c, "request_id": i + 1 }))?;
+        data.push(b'\n');
+    }
+    w.write_all(&data).await?;
+    w.flush().await?;
+
+    let mut results: Vec<Option<anyhow::Result<Value>>> = commands.iter().map(|_| None).collect();
+    let mut lines = BufReader::new(r).lines();
+    while results.iter().any(Option::is_none) {
+        let Some(line) = lines.next_line().await? else {
+            anyhow::bail!("mpv closed the connection");
+        };
+        let Ok(reply) = serde_json::from_str::<Reply>(&line) else {
+            continue;
+        };
+        let Some(slot) = reply
+            .request_id
+            .and_then(|id| results.get_mut(id.wrapping_sub(1)))
+        else {
+            continue;
+        };
+        *slot = Some(match reply.error.as_deref() {
+            Some("success") => Ok(reply.data),
+            e => Err(anyhow::anyhow!("mpv {}", e.unwrap_or("no error field"))),
+        });
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+#[cfg(unix)]
+async fn connect(socket: &Path) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(socket).await
+}
+
+#[cfg(windows)]
+async fn connect(
+    socket: &Path,
+) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(socket)
+}
+
+async fn send(socket: &Path, commands: &[Value]) -> anyhow::Result<Vec<anyhow::Result<Value>>> {
+    tokio::time::timeout(TIMEOUT, async {
+        let stream = connect(socket).await?;
+        requests(stream, commands).await
+    })
+    .await?
+}
+
+async fn command(socket: &Path, command: Value) -> anyhow::Result<Value> {
+    send(socket, &[command])
+        .await?
+        .pop()
+        .unwrap_or_else(|| Err(anyhow::anyhow!("no reply")))
+}
+
+/// 窗口刚打开时 socket 可能还没有创建, 连接失败时逐渐加长间隔重试
+pub async fn wait_ready(socket: &Path) -> anyhow::Result<()> {
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    let mut wait = Duration::from_millis(100);
+    loop {
+        match connect(socket).await {
+            Ok(_) => return Ok(()),
+            Err(e) if tokio::time::Instant::now() + wait < deadline => {
+                log::debug!("mpv {} not ready: {}", socket.display(), e);
+                tokio::time::sleep(wait).await;
+                wait = (wait * 2).min(Duration::from_secs(1));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// 在已打开的窗口中播放新的地址
+pub async fn load(socket: PathBuf, url: String) -> anyhow::Result<()> {
+    command(&socket, json!(["loadfile", url, "replace"])).await?;
+    Ok(())
+}
+
+pub async fn control(socket: PathBuf, control: PlayerControl) -> anyhow::Result<()> {
+    let c = match control {
+        PlayerControl::VolumeUp => json!(["add", "volume", VOLUME_STEP]),
+        PlayerControl::VolumeDown => json!(["add", "volume", -VOLUME_STEP]),
+        PlayerControl::ToggleMute => json!(["cycle", "mute"]),
+    };
+    command(&socket, c).await?;
+    Ok(())
+}
+
+/// 连接失败时返回错误, 单个属性读取失败时使用默认值
+pub async fn state(socket: PathBuf) -> anyhow::Result<PlaybackState> {
+    let props = ["paused-for-cache", "frame-drop-count", "volume", "mute"];
+    let commands: Vec<Value> = props.iter().map(|p| json!(["get_property", p])).collect();
+    let mut r = send(&socket, &commands).await?.into_iter().map(Result::ok);
+    let mut next = || r.next().flatten();
+    Ok(PlaybackState {
+        buffering: next().and_then(|v| v.as_bool()).unwrap_or(false),
+        dropped_frames: next().and_then(|v| v.as_u64()),
+        volume: next().and_then(|v| v.as_f64()).unwrap_or(0.0),
+        mute: next().and_then(|v| v.as_bool()).unwrap_or(false),
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::UnixListener,
+    };
+
+    use super::{control, load, socket_path, state, wait_ready, PlaybackState, PlayerControl};
+
+    // 模拟 mpv 的 IPC socket, 记录收到的命令
+    fn fake_mpv(path: &std::path::Path) -> Arc<Mutex<Vec<Value>>> {
+        std::fs::remove_file(path).ok();
+        let listener = UnixListener::bind(path).unwrap();
+        let received = Arc::new(Mutex::new(vec![]));
+        let r = received.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let r = r.clone();
+                tokio::spawn(async move {
+                    let (rd, mut wr) = stream.into_split();
+                    let mut lines = BufReader::new(rd).lines();
+                    // 先推送一个事件, 客户端应当跳过
+                    wr.write_all(b"{\"event\":\"idle\"}\n").await.unwrap();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let req: Value = serde_json::from_str(&line).unwrap();
+                        let id = req["request_id"].clone();
+                        let cmd = req["command"].clone();
+                        r.lock().unwrap().push(cmd.clone());
+                        let reply = match (cmd[0].as_str(), cmd[1].as_str()) {
+                            (Some("get_property"), Some("paused-for-cache")) => {
+                                serde_json::json!({"request_id": id, "error": "success", "data": true})
+                            }
+                            (Some("get_property"), Some("frame-drop-count")) => {
+                                serde_json::json!({"request_id": id, "error": "property unavailable"})
+                            }
+                            (Some("get_property"), Some("volume")) => {
+                                serde_json::json!({"request_id": id, "error": "success", "data": 70.0})
+                            }
+                            (Some("get_property"), Some("mute")) => {
+                                serde_json::json!({"request_id": id, "error": "success", "data": false})
+                            }
+                            _ => {
+                                serde_json::json!({"request_id": id, "error": "success", "data": null})
+                            }
+                        };
+                        let mut data = serde_json::to_vec(&reply).unwrap();
+                        data.push(b'\n');
+                        wr.write_all(&data).await.unwrap();
+                    }
+                });
+            }
+        });
+        received
+    }
+
+    #[tokio::test]
+    async fn test_ipc() {
+        let path = socket_path("test");
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with(&format!("seamui-mpv-{}-", std::process::id())));
+        let received = fake_mpv(&path);
+
+        load(path.clone(), "http://x/b.flv".into()).await.unwrap();
+        control(path.clone(), PlayerControl::ToggleMute)
+            .await
+            .unwrap();
+        let s = state(path.clone()).await.unwrap();
+        assert_eq!(
+            s,
+            PlaybackState {
+                buffering: true,
+                dropped_frames: None,
+                volume: 70.0,
+                mute: false,
+            }
+        );
+        let received = received.lock().unwrap().clone();
+        assert_eq!(
+            received[0],
+            serde_json::json!(["loadfile", "http://x/b.flv", "replace"])
+        );
+        assert_eq!(received[1], serde_json::json!(["cycle", "mute"]));
+        assert_eq!(received.len(), 6);
+        std::fs::remove_file(&path).ok();
+
+        // 窗口已关闭
+        assert!(load(path, "http://x/c.flv".into()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready() {
+        let path = socket_path("starting");
+        std::fs::remove_file(&path).ok();
+        let p = path.clone();
+        // 模拟 mpv 启动后才创建 socket
+        let late = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            fake_mpv(&p)
+        });
+        wait_ready(&path).await.unwrap();
+        let received = late.await.unwrap();
+        load(path.clone(), "http://x/d.flv".into()).await.unwrap();
+        assert_eq!(received.lock().unwrap().len(), 1);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,14 +1,73 @@
+use std::time::{Instant, SystemTime};
+
 use iced::{
     alignment::{Horizontal, Vertical},
-    widget::{column, container, scrollable},
-    Length, Renderer,
+    theme,
+    widget::{button, column, container, progress_bar, row, scrollable, text, text_input},
+    Application, Length, Renderer,
 };
 
 use super::{
-    anchor_input::AnchorInput, anchor_item::AnchorItem, cfg_panel::CfgPanel, Message, SeamUI,
+    anchor_input::AnchorInput, anchor_item::AnchorItem, cfg_panel::CfgPanel, i18n::tr, logs,
+    model::ShowType, theme::SelectedItem, uitl::format_age, BulkAction, Message, SeamUI,
 };
 
+/// 顶部标签栏中的页面, 切换时各页面的状态都保存在 `SeamUI` 中
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    #[default]
+    Anchors,
+    History,
+    Recordings,
+    Logs,
+    Settings,
+}
+
+impl Page {
+    pub const ALL: &'static [Page] = &[
+        Page::Anchors,
+        Page::History,
+        Page::Recordings,
+        Page::Logs,
+        Page::Settings,
+    ];
+
+    pub fn as_ui_text(&self) -> &'static str {
+        match self {
+            Page::Anchors => tr("page.anchors"),
+            Page::History => tr("page.history"),
+            Page::Recordings => tr("page.recordings"),
+            Page::Logs => tr("page.logs"),
+            Page::Settings => tr("page.settings"),
+        }
+    }
+}
+
 impl SeamUI {
+    pub fn tab_bar(
+        &self,
+    ) -> iced::Element<
+        '_,
+        <SeamUI as iced::Application>::Message,
+        iced::Renderer<<SeamUI as iced::Application>::Theme>,
+    > {
+        let tabs = Page::ALL.iter().map(|&p| {
+            let style = if p == self.page {
+                theme::Button::Primary
+            } else {
+                theme::Button::Text
+            };
+            button(text(p.as_ui_text()))
+                .style(style)
+                .on_press(Message::OnPage(p))
+                .into()
+        });
+        row(tabs.collect())
+            .spacing(5)
+            .padding([10, 10, 0, 10])
+            .into()
+    }
+
     pub fn main_page_view(
         &self,
     ) -> iced::Element<
@@ -16,39 +75,244 @@ impl SeamUI {
         <SeamUI as iced::Application>::Message,
         iced::Renderer<<SeamUI as iced::Application>::Theme>,
     > {
+        let palette = self.theme().palette();
         let anchor_input = AnchorInput::new(self.anchor_input_state.borrow_mut())
             .on_submit(Message::SubmitAnchor)
             .on_flush(|| Message::OnFlush)
-            .on_setting(|| Message::OnSetting);
-
-        let es: Vec<AnchorItem<Message>> = self
-            .anchor_list
-            .iter()
-            .enumerate()
-            .map(|(i, item)| -> AnchorItem<Message> {
-                AnchorItem::new(item)
+            .on_setting(|| Message::OnPage(Page::Settings))
+            .on_select(|| Message::OnSelectMode)
+            .palette(palette);
+
+        let compact = self.config.compact_mode;
+        let select_mode = self.select_mode;
+        let es: Vec<(usize, AnchorItem<Message>)> = self
+            .visible()
+            .map(|i| -> (usize, AnchorItem<Message>) {
+                let item = &self.anchor_list[i];
+                let item = AnchorItem::new(item)
                     .on_play(move |v| Message::OnPlay(i, v))
                     .on_update(move |v| Message::OnItemUpdate(i, v))
+                    .on_line_switch(move |v| Message::OnLiveLineSwitch(i, v))
+                    .on_check(move |v| Message::OnCheck(i, v))
+                    .on_player_control(move |v| Message::OnPlayerControl(i, v))
+                    .playback(
+                        self.mpv_sessions
+                            .get(&self.anchor_list[i].key())
+                            .and_then(|s| s.state.clone()),
+                    )
+                    .checked(select_mode.then(|| self.checked.contains(&i)))
+                    .palette(palette)
+                    .thumbnail(if compact {
+                        None
+                    } else {
+                        self.thumbnails
+                            .get(&self.anchor_list[i].key())
+                            .and_then(|(_, h)| h.clone())
+                    });
+                (i, item)
+            })
+            .collect();
+        let es: Vec<iced_native::Element<Message, Renderer>> = es
+            .into_iter()
+            .map(|(i, e)| {
+                let style = if self.selected == Some(i) {
+                    theme::Container::Custom(Box::new(SelectedItem))
+                } else {
+                    theme::Container::Box
+                };
+                container(e)
+                    .style(style)
+                    .width(Length::Fill)
+                    .padding(5)
+                    .into()
             })
             .collect();
-        let es: Vec<iced_native::Element<Message, Renderer>> =
-            es.into_iter().map(|e| e.into()).collect();
 
         let c = column(es).align_items(iced::Alignment::Start).spacing(15);
 
-        let content = iced_native::column!(
-            anchor_input,
-            scrollable(container(c).width(Length::Fill).padding([0, 6, 0, 6]))
-        )
-        .align_items(iced::Alignment::Center)
-        .padding(10)
-        .spacing(20);
+        // 普通模式下也可以按备注和自定义字段筛选
+        let filter = text_input(tr("main.filter"), &self.filter)
+            .on_input(Message::OnFilter)
+            .size(14);
+        let mut content = iced_native::column!(anchor_input, filter);
+        let depth = self.server_stats.queue_depth();
+        if let Some(r) = &self.refresh {
+            let done = r.total - r.waiting.len();
+            let progress = row!(
+                text(format!("{} {}/{}", tr("main.refreshing"), done, r.total)).size(14),
+                progress_bar(0.0..=r.total as f32, done as f32).height(Length::Fixed(6.0)),
+            )
+            .spacing(10)
+            .align_items(iced::Alignment::Center);
+            content = content.push(progress);
+        } else if depth > 0 {
+            content = content.push(text(format!("{} {}", tr("main.querying"), depth)).size(14));
+        }
+        if self.player_missing {
+            let warning = row!(
+                text(format!(
+                    "{} {}",
+                    tr("main.player_missing"),
+                    self.config.player_path
+                ))
+                .size(14)
+                .style(palette.danger)
+                .width(Length::Fill),
+                button(text(tr("page.settings")).size(14))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::OnPage(Page::Settings)),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            content = content.push(warning);
+        }
+        if let Some(d) = &self.draft {
+            let status = match &d.anchor.show_type {
+                _ if d.anchor.checking => tr("item.checking"),
+                Some(ShowType::On(_)) => tr("item.live"),
+                Some(ShowType::Off) => tr("item.off"),
+                Some(ShowType::Error(_)) => tr("item.error"),
+                _ => tr("item.pending"),
+            };
+            let draft = row!(
+                text(format!("{} {}", tr("main.add"), d.anchor.source_text())),
+                text_input(tr("item.name"), &d.anchor.name)
+                    .on_input(Message::OnDraftName)
+                    .on_submit(Message::OnDraftConfirm(true))
+                    .width(Length::Fill),
+                text(status).size(14),
+                button(text(tr("main.ok")))
+                    .style(theme::Button::Primary)
+                    .on_press(Message::OnDraftConfirm(true)),
+                button(text(tr("main.cancel")))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::OnDraftConfirm(false)),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            content = content.push(draft);
+        }
+        if let Some(a) = self.pending_delete.and_then(|i| self.anchor_list.get(i)) {
+            let confirm = row!(
+                text(format!("{} {}?", tr("main.confirm_delete"), a.name)),
+                row!().width(Length::Fill),
+                button(text(tr("main.ok")))
+                    .style(theme::Button::Destructive)
+                    .on_press(Message::OnDeleteConfirm(true)),
+                button(text(tr("main.cancel")))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::OnDeleteConfirm(false)),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            content = content.push(confirm);
+        }
+        if self.select_mode {
+            content = content.push(self.bulk_bar());
+        }
+        if let Some(c) = &self.reload_conflict {
+            let conflict = row!(
+                text(&c.text).size(14).width(Length::Fill),
+                button(text(tr("reload.keep_local")).size(14))
+                    .style(theme::Button::Primary)
+                    .on_press(Message::OnReloadConflict(true)),
+                button(text(tr("reload.use_disk")).size(14))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::OnReloadConflict(false)),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            content = content.push(conflict);
+        }
+        if let Some(change) = self.undo.bar(Instant::now()) {
+            let undo = row!(
+                text(change.as_ui_text(&self.anchor_list)).size(14),
+                row!().width(Length::Fill),
+                button(text(tr("main.undo")))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::Undo),
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center);
+            content = content.push(undo);
+        }
+        let content = content
+            .push(scrollable(
+                container(c).width(Length::Fill).padding([0, 6, 0, 6]),
+            ))
+            .align_items(iced::Alignment::Center)
+            .padding(10)
+            .spacing(20);
 
         container(content)
             .align_x(Horizontal::Center)
             .align_y(Vertical::Top)
             .into()
     }
+    fn bulk_bar(
+        &self,
+    ) -> iced::Element<
+        '_,
+        <SeamUI as iced::Application>::Message,
+        iced::Renderer<<SeamUI as iced::Application>::Theme>,
+    > {
+        let small = |s: &'static str, m: Message| {
+            button(text(tr(s)).size(14))
+                .style(theme::Button::Secondary)
+                .on_press(m)
+        };
+        let filter = row!(
+            row!().width(Length::Fill),
+            small("bulk.select_all", Message::OnSelectAll),
+            small("bulk.select_filtered", Message::OnSelectFiltered),
+            small("bulk.clear", Message::OnClearSelection),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        let actions = row!(
+            text(format!("{} {}", tr("bulk.selected"), self.checked.len())).size(14),
+            row!().width(Length::Fill),
+            small("bulk.refresh", Message::OnBulk(BulkAction::Refresh)),
+            small("bulk.play", Message::OnBulk(BulkAction::PlayLive)),
+            small("bulk.export", Message::OnBulk(BulkAction::Export)),
+            button(text(tr("bulk.delete")).size(14))
+                .style(theme::Button::Destructive)
+                .on_press(Message::OnBulk(BulkAction::Delete)),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        let group = row!(
+            text_input(tr("bulk.group"), &self.group_input)
+                .on_input(Message::OnGroupInput)
+                .on_submit(Message::OnBulk(BulkAction::MoveToGroup))
+                .width(Length::Fill),
+            small(
+                "bulk.move_to_group",
+                Message::OnBulk(BulkAction::MoveToGroup)
+            ),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        let import = row!(
+            text_input(tr("bulk.import_path"), &self.import_path)
+                .on_input(Message::OnImportPath)
+                .on_submit(Message::OnImport)
+                .width(Length::Fill),
+            small("bulk.import", Message::OnImport),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        let mut c = column!(filter, actions, group, import).spacing(5);
+        if let Some(n) = &self.notice {
+            c = c.push(text(n).size(14));
+        }
+        c.into()
+    }
+
     pub fn setting_view(
         &self,
     ) -> iced::Element<
@@ -56,7 +320,21 @@ impl SeamUI {
         <SeamUI as iced::Application>::Message,
         iced::Renderer<<SeamUI as iced::Application>::Theme>,
     > {
-        let panel = CfgPanel::new(&self.config).on_update(Message::OnSettingUpdate);
+        let panel = CfgPanel::new(
+            self.cfg_panel_state.borrow_mut(),
+            &self.config,
+            &self.credentials,
+            &self.cookie_status,
+        )
+        .on_update(Message::OnSettingUpdate)
+        .on_credentials(Message::OnCredentialsUpdate)
+        .on_check_cookie(Message::OnCheckCookie)
+        .profiles(&self.profiles)
+        .on_profile(Message::OnProfile)
+        .players(&self.players)
+        .on_detect_players(|| Message::OnDetectPlayers)
+        .player_missing(self.player_missing)
+        .palette(self.theme().palette());
         let c = column!(panel)
             .align_items(iced::Alignment::Center)
             .padding(10)
@@ -67,4 +345,114 @@ impl SeamUI {
             .align_y(Vertical::Top)
             .into()
     }
+
+    pub fn history_view(
+        &self,
+    ) -> iced::Element<
+        '_,
+        <SeamUI as iced::Application>::Message,
+        iced::Renderer<<SeamUI as iced::Application>::Theme>,
+    > {
+        let now = SystemTime::now();
+        let items = self.history.iter().map(|h| {
+            row!(
+                text(format_age(h.at, now))
+                    .size(14)
+                    .width(Length::Fixed(90.0)),
+                text(&h.name).width(Length::FillPortion(2)),
+                text(&h.source).size(14).width(Length::FillPortion(3)),
+                text(h.as_ui_text()).size(14).width(Length::FillPortion(3)),
+            )
+            .spacing(10)
+            .align_items(iced::Alignment::Center)
+            .into()
+        });
+        let header = row!(
+            text(tr("history.tip")).size(14),
+            row!().width(Length::Fill),
+            button(text(tr("bulk.clear")).size(14))
+                .style(theme::Button::Secondary)
+                .on_press(Message::OnClearHistory),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+        let mut content = column!(header);
+        if self.history.is_empty() {
+            content = content.push(text(tr("history.empty")));
+        }
+        self.list_page(content, column(items.collect()))
+    }
+
+    pub fn recordings_view(
+        &self,
+    ) -> iced::Element<
+        '_,
+        <SeamUI as iced::Application>::Message,
+        iced::Renderer<<SeamUI as iced::Application>::Theme>,
+    > {
+        self.list_page(column!(text(tr("recordings.empty"))), column!())
+    }
+
+    pub fn logs_view(
+        &self,
+    ) -> iced::Element<
+        '_,
+        <SeamUI as iced::Application>::Message,
+        iced::Renderer<<SeamUI as iced::Application>::Theme>,
+    > {
+        let palette = self.theme().palette();
+        let now = SystemTime::now();
+        let q = self.log_filter.trim().to_lowercase();
+        let items = logs::recent()
+            .into_iter()
+            .filter(|l| q.is_empty() || l.text.to_lowercase().contains(&q))
+            .map(|l| {
+                let level = text(l.level).size(14).width(Length::Fixed(50.0));
+                let level = match l.level {
+                    log::Level::Error => level.style(palette.danger),
+                    log::Level::Warn => level.style(palette.primary),
+                    _ => level,
+                };
+                row!(
+                    text(format_age(l.at, now))
+                        .size(14)
+                        .width(Length::Fixed(90.0)),
+                    level,
+                    text(l.text).size(14).width(Length::Fill),
+                )
+                .spacing(10)
+                .into()
+            });
+        let header = row!(
+            text_input(tr("logs.filter"), &self.log_filter)
+                .on_input(Message::OnLogFilter)
+                .width(Length::Fill),
+            button(text(tr("bulk.clear")).size(14))
+                .style(theme::Button::Secondary)
+                .on_press(Message::OnClearLogs),
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+        self.list_page(column!(header), column(items.collect()))
+    }
+
+    // 固定的页头加可滚动的列表
+    fn list_page<'a>(
+        &self,
+        header: iced::widget::Column<'a, Message, iced::Renderer<iced::Theme>>,
+        list: iced::widget::Column<'a, Message, iced::Renderer<iced::Theme>>,
+    ) -> iced::Element<'a, Message, iced::Renderer<iced::Theme>> {
+        let content = header
+            .push(scrollable(
+                container(list.spacing(8))
+                    .width(Length::Fill)
+                    .padding([0, 6, 0, 6]),
+            ))
+            .padding(10)
+            .spacing(20);
+        container(content)
+            .align_x(Horizontal::Center)
+            .align_y(Vertical::Top)
+            .into()
+    }
 }
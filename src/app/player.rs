@@ -0,0 +1,218 @@
+//! 检测常见播放器, 提供默认参数作为预设
+
+use std::{
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+struct Known {
+    name: &'static str,
+    // 在 PATH 中查找的可执行文件名
+    bins: &'static [&'static str],
+    // 常见安装位置, 只检查当前系统下的绝对路径
+    paths: &'static [&'static str],
+    args: &'static [&'static str],
+}
+
+const KNOWN: &[Known] = &[
+    Known {
+        name: "mpv",
+        bins: &["mpv"],
+        paths: &[
+            "/Applications/mpv.app/Contents/MacOS/mpv",
+            "/opt/homebrew/bin/mpv",
+            "/snap/bin/mpv",
+            r"C:\Program Files\mpv\mpv.exe",
+        ],
+        args: &["--no-ytdl", "--force-window=immediate"],
+    },
+    Known {
+        name: "vlc",
+        bins: &["vlc"],
+        paths: &[
+            "/Applications/VLC.app/Contents/MacOS/VLC",
+            "/snap/bin/vlc",
+            r"C:\Program Files\VideoLAN\VLC\vlc.exe",
+            r"C:\Program Files (x86)\VideoLAN\VLC\vlc.exe",
+        ],
+        args: &["--play-and-exit"],
+    },
+    Known {
+        name: "ffplay",
+        bins: &["ffplay"],
+        paths: &["/opt/homebrew/bin/ffplay"],
+        args: &["-autoexit", "-loglevel", "warning"],
+    },
+    Known {
+        name: "celluloid",
+        bins: &["celluloid"],
+        paths: &[],
+        args: &["--new-window"],
+    },
+    Known {
+        name: "iina",
+        bins: &["iina-cli", "iina"],
+        paths: &["/Applications/IINA.app/Contents/MacOS/iina-cli"],
+        args: &[],
+    },
+    Known {
+        name: "potplayer",
+        bins: &["PotPlayerMini64", "PotPlayerMini"],
+        paths: &[
+            r"C:\Program Files\DAUM\PotPlayer\PotPlayerMini64.exe",
+            r"C:\Program Files (x86)\DAUM\PotPlayer\PotPlayerMini.exe",
+            r"C:\Program Files\PotPlayer\PotPlayerMini64.exe",
+        ],
+        args: &[],
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerPreset {
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for PlayerPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.path.display())
+    }
+}
+
+/// 在 PATH 和常见安装位置中查找播放器, 每种播放器只取第一个
+pub fn detect() -> Vec<PlayerPreset> {
+    detect_in(std::env::var_os("PATH"))
+}
+
+fn detect_in(path_var: Option<OsString>) -> Vec<PlayerPreset> {
+    KNOWN
+        .iter()
+        .filter_map(|k| {
+            let path = k
+                .bins
+                .iter()
+                .find_map(|b| search_path(b, path_var.as_deref()))
+                .or_else(|| {
+                    k.paths
+                        .iter()
+                        .map(PathBuf::from)
+                        .find(|p| p.is_absolute() && is_executable(p))
+                })?;
+            Some(PlayerPreset {
+                name: k.name,
+                path,
+                args: k.args.iter().map(|s| s.to_string()).collect(),
+            })
+        })
+        .collect()
+}
+
+fn search_path(bin: &str, path_var: Option<&std::ffi::OsStr>) -> Option<PathBuf> {
+    let exts: &[&str] = if cfg!(windows) {
+        &["exe", "cmd", "bat"]
+    } else {
+        &[]
+    };
+    std::env::split_paths(path_var?).find_map(|dir| {
+        let p = dir.join(bin);
+        if is_executable(&p) {
+            return Some(p);
+        }
+        exts.iter()
+            .map(|e| p.with_extension(e))
+            .find(|p| is_executable(p))
+    })
+}
+
+fn is_executable(p: &Path) -> bool {
+    let Ok(meta) = std::fs::metadata(p) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        meta.is_file()
+    }
+}
+
+/// 配置的播放器能否执行, 可以是路径或 PATH 中的命令名
+pub fn find_executable(player: &str) -> Option<PathBuf> {
+    let player = player.trim();
+    if player.is_empty() {
+        return None;
+    }
+    let p = Path::new(player);
+    if p.components().count() > 1 || p.is_absolute() {
+        return is_executable(p).then(|| p.to_path_buf());
+    }
+    search_path(player, std::env::var_os("PATH").as_deref())
+}
+
+/// 播放器是否为 mpv, 只有 mpv 支持 JSON IPC
+pub fn is_mpv(player: &str) -> bool {
+    Path::new(player.trim())
+        .file_stem()
+        .is_some_and(|s| s.eq_ignore_ascii_case("mpv"))
+}
+
+/// 播放器参数, `{url}` 替换为直播流地址, 没有 `{url}` 时地址放在最后
+pub fn command_args(args: &[String], url: &str) -> Vec<String> {
+    let mut out: Vec<String> = args.iter().map(|a| a.replace("{url}", url)).collect();
+    if !args.iter().any(|a| a.contains("{url}")) {
+        out.push(url.to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_args, detect_in, find_executable, is_mpv};
+
+    #[test]
+    fn test_command_args() {
+        let url = "http://x/a.flv";
+        assert_eq!(command_args(&[], url), [url]);
+        let args = vec!["--no-ytdl".to_string()];
+        assert_eq!(command_args(&args, url), ["--no-ytdl", url]);
+        let args = vec!["-i".to_string(), "{url}".to_string(), "-x".to_string()];
+        assert_eq!(command_args(&args, url), ["-i", url, "-x"]);
+
+        assert!(is_mpv("mpv"));
+        assert!(is_mpv("/usr/bin/mpv"));
+        assert!(!is_mpv("vlc"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("seamui-player-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, mode) in [("mpv", 0o755), ("vlc", 0o644)] {
+            let p = dir.join(name);
+            std::fs::write(&p, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&p, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let found = detect_in(Some(dir.clone().into_os_string()));
+        let mpv = found.iter().find(|p| p.name == "mpv").unwrap();
+        assert_eq!(mpv.path, dir.join("mpv"));
+        assert!(mpv.args.contains(&"--no-ytdl".to_string()));
+        // 没有执行权限的不算
+        assert!(found.iter().all(|p| p.path != dir.join("vlc")));
+
+        assert_eq!(
+            find_executable(dir.join("mpv").to_str().unwrap()),
+            Some(dir.join("mpv"))
+        );
+        assert_eq!(find_executable(dir.join("vlc").to_str().unwrap()), None);
+        assert_eq!(find_executable(""), None);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,128 @@
+//! 直链主播的在线检测, 只判断地址是否可用, 不解析内容
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use tokio::net::TcpStream;
+
+use super::{
+    model::{Node, SeamInfo, ShowType},
+    proxy::http_client,
+};
+
+pub async fn probe(url: &str, proxy: Option<&str>, timeout: Duration) -> ShowType {
+    let r = if url.starts_with("rtmp://") || url.starts_with("rtmps://") {
+        tokio::time::timeout(timeout, probe_rtmp(url)).await
+    } else {
+        tokio::time::timeout(timeout, probe_http(url, proxy)).await
+    };
+    match r {
+        Ok(Ok(true)) => ShowType::On(SeamInfo {
+            title: String::new(),
+            nodes: Some(vec![Node {
+                format: format_of(url),
+                url: url.to_string(),
+            }]),
+            cover: None,
+            avatar: None,
+            nickname: None,
+        }),
+        Ok(Ok(false)) => ShowType::Off,
+        Ok(Err(e)) => ShowType::Error(e.to_string()),
+        Err(_) => ShowType::Error(format!("timeout after {}s", timeout.as_secs())),
+    }
+}
+
+fn format_of(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    if url.starts_with("rtmp") {
+        "rtmp".into()
+    } else if path.ends_with(".m3u8") {
+        "m3u".into()
+    } else if path.ends_with(".flv") {
+        "flv".into()
+    } else {
+        path.rsplit_once('.')
+            .map(|(_, ext)| ext)
+            .filter(|ext| !ext.contains('/'))
+            .unwrap_or("http")
+            .to_string()
+    }
+}
+
+// flv 是持续的流, 只看响应头; m3u8 还要确认内容是播放列表
+async fn probe_http(url: &str, proxy: Option<&str>) -> anyhow::Result<bool> {
+    let resp = http_client(proxy)?.get(url).send().await?;
+    let status = resp.status();
+    if matches!(
+        status,
+        StatusCode::NOT_FOUND | StatusCode::GONE | StatusCode::FORBIDDEN
+    ) {
+        return Ok(false);
+    }
+    if !status.is_success() {
+        anyhow::bail!("http {}", status);
+    }
+    if format_of(url) == "m3u" {
+        return Ok(resp.text().await?.trim_start().starts_with("#EXTM3U"));
+    }
+    Ok(true)
+}
+
+// 端口能连上即认为在推流
+async fn probe_rtmp(url: &str) -> anyhow::Result<bool> {
+    let u = reqwest::Url::parse(url)?;
+    let host = u
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("no host in {}", url))?;
+    let port = u
+        .port()
+        .unwrap_or(if u.scheme() == "rtmps" { 443 } else { 1935 });
+    match TcpStream::connect((host, port)).await {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::probe;
+    use crate::app::{model::ShowType, test_http};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn test_probe_http() {
+        let hls = test_http::spawn(200, b"#EXTM3U\n#EXT-X-VERSION:3\n").await;
+        match probe(&hls.url("/live/a.m3u8?t=1"), None, TIMEOUT).await {
+            ShowType::On(s) => assert_eq!(s.nodes.unwrap()[0].format, "m3u"),
+            v => panic!("{:?}", v),
+        }
+
+        let gone = test_http::spawn(404, b"").await;
+        assert!(matches!(
+            probe(&gone.url("/live/a.flv"), None, TIMEOUT).await,
+            ShowType::Off
+        ));
+
+        let broken = test_http::spawn(500, b"").await;
+        assert!(matches!(
+            probe(&broken.url("/live/a.flv"), None, TIMEOUT).await,
+            ShowType::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_probe_rtmp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("rtmp://{}/live/stream", addr);
+        assert!(matches!(probe(&url, None, TIMEOUT).await, ShowType::On(_)));
+
+        drop(listener);
+        assert!(matches!(probe(&url, None, TIMEOUT).await, ShowType::Off));
+    }
+}
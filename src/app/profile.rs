@@ -0,0 +1,234 @@
+//! 配置文件的位置, 按优先级: `--config` 参数, `SEAMUI_CONFIG` 环境变量, 便携模式, 系统数据目录。
+//! 每个命名配置在 `profiles/<名称>/` 下有独立的主播列表、设置和 cookie
+
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+pub const CONFIG_ENV: &str = "SEAMUI_CONFIG";
+pub const PROFILE_ENV: &str = "SEAMUI_PROFILE";
+// 可执行文件旁有该文件时使用便携模式
+pub const PORTABLE_MARKER: &str = "portable";
+const FILE_NAME: &str = "seamui.json";
+
+static CURRENT: RwLock<Option<Location>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    // 默认配置的 seamui.json, 命名配置保存在同目录的 profiles 下
+    pub default_file: PathBuf,
+    pub profile: Option<String>,
+    pub portable: bool,
+}
+
+impl Location {
+    pub fn path(&self) -> PathBuf {
+        match &self.profile {
+            Some(p) => self.profiles_dir().join(p).join(FILE_NAME),
+            None => self.default_file.clone(),
+        }
+    }
+
+    fn profiles_dir(&self) -> PathBuf {
+        self.default_file
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("profiles")
+    }
+
+    /// 已创建的命名配置, 按名称排序
+    pub fn profiles(&self) -> Vec<String> {
+        let Ok(dir) = std::fs::read_dir(self.profiles_dir()) else {
+            return vec![];
+        };
+        let mut v: Vec<String> = dir
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| valid_name(n))
+            .collect();
+        v.sort();
+        v
+    }
+}
+
+/// 配置名会作为目录名使用
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':'])
+        && name.trim() == name
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub portable: bool,
+}
+
+/// 解析命令行参数 `--config <路径>`, `--profile <名称>`, `--portable`
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Args> {
+    let mut out = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) => (f.to_string(), Some(v.to_string())),
+            None => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", name))
+        };
+        match flag.as_str() {
+            "--config" => out.config = Some(value("--config")?.into()),
+            "--profile" => out.profile = Some(value("--profile")?),
+            "--portable" => out.portable = true,
+            _ => anyhow::bail!(
+                "unknown argument {}, usage: seamui [--config <path>] [--profile <name>] [--portable]",
+                flag
+            ),
+        }
+    }
+    Ok(out)
+}
+
+/// `env` 读取环境变量, `exe_dir` 是可执行文件所在目录
+pub fn resolve(
+    args: Args,
+    env: impl Fn(&str) -> Option<String>,
+    exe_dir: Option<&Path>,
+) -> anyhow::Result<Location> {
+    let env = |k: &str| env(k).filter(|v| !v.trim().is_empty());
+    let config = args.config.or_else(|| env(CONFIG_ENV).map(PathBuf::from));
+    let portable_dir = exe_dir.filter(|d| args.portable || d.join(PORTABLE_MARKER).exists());
+    let (default_file, portable) = match (config, portable_dir) {
+        (Some(c), _) if c.is_dir() => (c.join(FILE_NAME), false),
+        (Some(c), _) => (c, false),
+        (None, Some(d)) => (d.join("data").join(FILE_NAME), true),
+        (None, None) => (default_file(), false),
+    };
+    let profile = args.profile.or_else(|| env(PROFILE_ENV));
+    if let Some(p) = &profile {
+        if !valid_name(p) {
+            anyhow::bail!("invalid profile name {:?}", p);
+        }
+    }
+    Ok(Location {
+        default_file,
+        profile,
+        portable,
+    })
+}
+
+fn default_file() -> PathBuf {
+    let mut p: PathBuf = directories_next::ProjectDirs::from("", "", "seamui")
+        .expect("cant find path")
+        .data_dir()
+        .into();
+    p.push(FILE_NAME);
+    p
+}
+
+pub fn init(loc: Location) {
+    *CURRENT.write().unwrap() = Some(loc);
+}
+
+/// 当前使用的位置, 没有初始化时为系统数据目录
+pub fn current() -> Location {
+    CURRENT.read().unwrap().clone().unwrap_or_else(|| Location {
+        default_file: default_file(),
+        profile: None,
+        portable: false,
+    })
+}
+
+/// 切换到命名配置, None 为默认配置
+pub fn switch(profile: Option<String>) -> anyhow::Result<Location> {
+    if let Some(p) = &profile {
+        if !valid_name(p) {
+            anyhow::bail!("invalid profile name {:?}", p);
+        }
+    }
+    let mut loc = current();
+    loc.profile = profile;
+    init(loc.clone());
+    Ok(loc)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_args, resolve, valid_name, Args, CONFIG_ENV, PORTABLE_MARKER, PROFILE_ENV};
+
+    fn args(v: &[&str]) -> anyhow::Result<Args> {
+        parse_args(v.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(args(&[]).unwrap(), Args::default());
+        let a = args(&["--config", "/x/a.json", "--profile=work", "--portable"]).unwrap();
+        assert_eq!(a.config, Some(PathBuf::from("/x/a.json")));
+        assert_eq!(a.profile.as_deref(), Some("work"));
+        assert!(a.portable);
+        assert!(args(&["--config"]).is_err());
+        assert!(args(&["--nope"]).is_err());
+
+        assert!(valid_name("work"));
+        assert!(!valid_name("../x"));
+        assert!(!valid_name(""));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let no_env = |_: &str| None;
+        let l = resolve(args(&["--config", "/x/a.json"]).unwrap(), no_env, None).unwrap();
+        assert_eq!(l.path(), Path::new("/x/a.json"));
+        let l = resolve(
+            args(&["--config", "/x/a.json", "--profile", "work"]).unwrap(),
+            no_env,
+            None,
+        )
+        .unwrap();
+        assert_eq!(l.path(), Path::new("/x/profiles/work/seamui.json"));
+
+        // 参数优先于环境变量
+        let env = |k: &str| match k {
+            CONFIG_ENV => Some("/env/seamui.json".to_string()),
+            PROFILE_ENV => Some("home".to_string()),
+            _ => None,
+        };
+        let l = resolve(Args::default(), env, None).unwrap();
+        assert_eq!(l.path(), Path::new("/env/profiles/home/seamui.json"));
+        let l = resolve(args(&["--config", "/x/a.json"]).unwrap(), env, None).unwrap();
+        assert_eq!(l.default_file, Path::new("/x/a.json"));
+        assert!(resolve(args(&["--profile", ".."]).unwrap(), no_env, None).is_err());
+
+        let dir = std::env::temp_dir().join(format!("seamui-profile-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("profiles").join("b")).unwrap();
+        std::fs::create_dir_all(dir.join("profiles").join("a")).unwrap();
+        // 目录作为 --config 时使用其中的 seamui.json
+        let l = resolve(
+            args(&["--config", dir.to_str().unwrap()]).unwrap(),
+            no_env,
+            None,
+        )
+        .unwrap();
+        assert_eq!(l.default_file, dir.join("seamui.json"));
+        assert_eq!(l.profiles(), ["a", "b"]);
+
+        let l = resolve(Args::default(), no_env, Some(&dir)).unwrap();
+        assert!(!l.portable);
+        std::fs::write(dir.join(PORTABLE_MARKER), "").unwrap();
+        let l = resolve(Args::default(), no_env, Some(&dir)).unwrap();
+        assert!(l.portable);
+        assert_eq!(l.path(), dir.join("data").join("seamui.json"));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::model::Platform;
+
+/// 代理配置, 支持 `http://`, `https://`, `socks5://`, 空字符串表示不使用
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub global: String,
+    // 单个平台的代理, 优先于全局代理
+    pub platforms: HashMap<Platform, String>,
+}
+
+impl ProxyConfig {
+    pub fn for_platform(&self, platform: Option<Platform>) -> Option<&str> {
+        platform
+            .and_then(|p| self.platforms.get(&p))
+            .map(String::as_str)
+            .filter(|s| !s.trim().is_empty())
+            .or_else(|| Some(self.global.as_str()).filter(|s| !s.trim().is_empty()))
+    }
+}
+
+/// 传给播放器等子进程的环境变量, ffmpeg/mpv/vlc 都会读取
+pub fn proxy_env(proxy: &str) -> [(&'static str, String); 6] {
+    [
+        ("http_proxy", proxy.to_string()),
+        ("https_proxy", proxy.to_string()),
+        ("all_proxy", proxy.to_string()),
+        ("HTTP_PROXY", proxy.to_string()),
+        ("HTTPS_PROXY", proxy.to_string()),
+        ("ALL_PROXY", proxy.to_string()),
+    ]
+}
+
+pub fn http_client(proxy: Option<&str>) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(p) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(p)?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{http_client, ProxyConfig};
+    use crate::app::{model::Platform, test_http};
+
+    #[test]
+    fn test_for_platform() {
+        let mut cfg = ProxyConfig::default();
+        assert_eq!(cfg.for_platform(Some(Platform::HuYa)), None);
+
+        cfg.global = "http://127.0.0.1:8080".into();
+        cfg.platforms = HashMap::from([
+            (Platform::Afreeca, "socks5://127.0.0.1:1080".to_string()),
+            (Platform::DouYu, " ".to_string()),
+        ]);
+        assert_eq!(
+            cfg.for_platform(Some(Platform::Afreeca)),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(
+            cfg.for_platform(Some(Platform::DouYu)),
+            Some("http://127.0.0.1:8080")
+        );
+        assert_eq!(cfg.for_platform(None), Some("http://127.0.0.1:8080"));
+    }
+
+    #[tokio::test]
+    async fn test_http_client_via_proxy() {
+        let proxy = test_http::spawn(200, b"proxied").await;
+        let client = http_client(Some(&proxy.url(""))).unwrap();
+        let body = client
+            .get("http://stream.example.invalid/live.flv")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied");
+        let reqs = proxy.requests.lock().unwrap();
+        assert!(reqs[0].starts_with("GET http://stream.example.invalid/live.flv HTTP/1.1"));
+    }
+}
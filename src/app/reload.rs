@@ -0,0 +1,209 @@
+//! seamui.json 被外部修改时, 以上次读写的内容为基准合并磁盘和程序中的修改
+
+use std::collections::HashMap;
+
+use super::{
+    model::AnchorInfo,
+    uitl::{AppConfig, SavedState, WindowConfig},
+};
+
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub state: SavedState,
+    // 两边都修改过的主播名, 合并结果中保留程序中的版本
+    pub conflicts: Vec<String>,
+    // 两边都修改了设置, 保留程序中的设置
+    pub config_conflict: bool,
+}
+
+impl Merged {
+    pub fn has_conflict(&self) -> bool {
+        self.config_conflict || !self.conflicts.is_empty()
+    }
+}
+
+fn by_key(list: &[AnchorInfo]) -> HashMap<String, &AnchorInfo> {
+    let mut m = HashMap::new();
+    for a in list {
+        m.entry(a.key()).or_insert(a);
+    }
+    m
+}
+
+fn keys(list: &[AnchorInfo]) -> Vec<String> {
+    list.iter().map(AnchorInfo::key).collect()
+}
+
+// 使用磁盘上的内容, 保留程序中的查询结果
+fn with_status(mut theirs: AnchorInfo, ours: &AnchorInfo) -> AnchorInfo {
+    theirs.show_type = ours.show_type.clone();
+    theirs.live_line = ours.live_line;
+    theirs.checking = ours.checking;
+    theirs.updated_at = ours.updated_at;
+    theirs
+}
+
+// 窗口位置和大小各机器不同, 不参与合并
+fn without_window(c: &AppConfig) -> AppConfig {
+    AppConfig {
+        window: WindowConfig::default(),
+        ..c.clone()
+    }
+}
+
+/// 三方合并, `base` 是上次读取或保存的内容, 只有一边修改的内容直接采用,
+/// 两边修改不同时保留 `ours` 并记为冲突
+pub fn merge(base: &SavedState, ours: &SavedState, theirs: &SavedState) -> Merged {
+    let base_map = by_key(&base.anchors);
+    let ours_map = by_key(&ours.anchors);
+    let theirs_map = by_key(&theirs.anchors);
+    let mut merged = Merged::default();
+
+    let mut anchors = vec![];
+    for t in &theirs.anchors {
+        let k = t.key();
+        match (base_map.get(&k), ours_map.get(&k)) {
+            (_, Some(o)) if o.same_saved(t) => anchors.push((*o).clone()),
+            (Some(b), Some(o)) if o.same_saved(b) => anchors.push(with_status(t.clone(), o)),
+            (Some(b), Some(o)) if t.same_saved(b) => anchors.push((*o).clone()),
+            (_, Some(o)) => {
+                merged.conflicts.push(o.name.clone());
+                anchors.push((*o).clone());
+            }
+            // 程序中已删除, 磁盘上又修改过
+            (Some(b), None) if !t.same_saved(b) => merged.conflicts.push(t.name.clone()),
+            (Some(_), None) => {}
+            (None, None) => anchors.push(t.clone()),
+        }
+    }
+    for o in &ours.anchors {
+        let k = o.key();
+        if theirs_map.contains_key(&k) {
+            continue;
+        }
+        match base_map.get(&k) {
+            // 磁盘上已删除
+            Some(b) if o.same_saved(b) => {}
+            Some(_) => {
+                merged.conflicts.push(o.name.clone());
+                anchors.push(o.clone());
+            }
+            None => anchors.push(o.clone()),
+        }
+    }
+    // 磁盘上没有调整顺序时保持程序中的顺序
+    if keys(&theirs.anchors) == keys(&base.anchors) {
+        let pos: HashMap<String, usize> = ours
+            .anchors
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (a.key(), i))
+            .collect();
+        anchors.sort_by_key(|a| pos.get(&a.key()).copied().unwrap_or(usize::MAX));
+    }
+    merged.state.anchors = anchors;
+
+    let (b, o, t) = (&base.config, &ours.config, &theirs.config);
+    merged.state.config = if same_config(o, b) {
+        AppConfig {
+            window: o.window.clone(),
+            ..t.clone()
+        }
+    } else {
+        merged.config_conflict = !same_config(t, b) && !same_config(t, o);
+        o.clone()
+    };
+    merged
+}
+
+/// 除窗口外的设置是否相同
+pub fn same_config(a: &AppConfig, b: &AppConfig) -> bool {
+    without_window(a) == without_window(b)
+}
+
+/// 合并结果和磁盘上的内容是否相同, 不同时需要写回
+pub fn same_saved(a: &SavedState, b: &SavedState) -> bool {
+    a.anchors.len() == b.anchors.len()
+        && a.anchors
+            .iter()
+            .zip(&b.anchors)
+            .all(|(x, y)| x.same_saved(y))
+        && same_config(&a.config, &b.config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, same_saved};
+    use crate::app::{
+        model::{AnchorInfo, Platform, ShowType},
+        uitl::SavedState,
+    };
+
+    fn anchor(room: &str, name: &str) -> AnchorInfo {
+        AnchorInfo {
+            name: name.into(),
+            platform: Some(Platform::BiliBili),
+            room_id: room.into(),
+            ..Default::default()
+        }
+    }
+
+    fn state(anchors: Vec<AnchorInfo>) -> SavedState {
+        SavedState {
+            anchors,
+            ..Default::default()
+        }
+    }
+
+    fn names(s: &SavedState) -> Vec<&str> {
+        s.anchors.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = state(vec![anchor("1", "a"), anchor("2", "b"), anchor("3", "c")]);
+
+        // 程序中: 删除 b, 新增 d, 查询到 a 在直播
+        let mut ours = state(vec![anchor("1", "a"), anchor("3", "c"), anchor("4", "d")]);
+        ours.anchors[0].show_type = Some(ShowType::Off);
+        // 磁盘上: 改名 a, 新增 e, 修改设置
+        let mut theirs = state(vec![
+            anchor("1", "a2"),
+            anchor("2", "b"),
+            anchor("3", "c"),
+            anchor("5", "e"),
+        ]);
+        theirs.config.player_path = "vlc".into();
+        theirs.config.window.width = 1;
+
+        let m = merge(&base, &ours, &theirs);
+        assert!(!m.has_conflict());
+        assert_eq!(names(&m.state), ["a2", "c", "e", "d"]);
+        assert!(matches!(m.state.anchors[0].show_type, Some(ShowType::Off)));
+        assert_eq!(m.state.config.player_path, "vlc");
+        assert_eq!(m.state.config.window, ours.config.window);
+        assert!(!same_saved(&m.state, &theirs));
+
+        // 只有程序中调整了顺序
+        let ours = state(vec![anchor("3", "c"), anchor("1", "a"), anchor("2", "b")]);
+        let theirs = state(vec![anchor("1", "a"), anchor("2", "b2"), anchor("3", "c")]);
+        let m = merge(&base, &ours, &theirs);
+        assert_eq!(names(&m.state), ["c", "a", "b2"]);
+
+        // 两边都修改
+        let mut ours = state(vec![anchor("1", "x"), anchor("2", "b")]);
+        ours.config.player_path = "mpv2".into();
+        let mut theirs = state(vec![anchor("1", "y"), anchor("2", "b"), anchor("3", "c2")]);
+        theirs.config.player_path = "vlc".into();
+        let m = merge(&base, &ours, &theirs);
+        assert_eq!(m.conflicts, ["x", "c2"]);
+        assert!(m.config_conflict);
+        assert_eq!(names(&m.state), ["x", "b"]);
+        assert_eq!(m.state.config.player_path, "mpv2");
+
+        // 以程序中的内容为基准时等同于使用磁盘上的内容
+        let m = merge(&ours, &ours, &theirs);
+        assert!(!m.has_conflict());
+        assert!(same_saved(&m.state, &theirs));
+    }
+}
@@ -0,0 +1,381 @@
+use std::{collections::HashMap, process::Stdio, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::process;
+
+use super::{
+    model::{Node, Platform, SeamInfo},
+    proxy,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ResolverKind {
+    // seam 命令行, 输出即 SeamInfo
+    Seam,
+    // streamlink --json
+    Streamlink,
+    // yt-dlp -J
+    YtDlp,
+}
+
+impl ResolverKind {
+    pub fn default_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            ResolverKind::Seam => &["{platform}", "{room}"],
+            ResolverKind::Streamlink => &["--json", "{url}"],
+            ResolverKind::YtDlp => &["-J", "{url}"],
+        };
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    // 各工具未开播时输出的完整错误信息, 其余错误仍按失败处理和重试
+    fn offline_messages(&self) -> &'static [&'static str] {
+        match self {
+            ResolverKind::Seam => &["未开播"],
+            ResolverKind::Streamlink => &["no playable streams found on this url"],
+            ResolverKind::YtDlp => &["is not currently live", "this live event will begin"],
+        }
+    }
+
+    fn is_offline_message(&self, s: &str) -> bool {
+        let s = s.to_lowercase();
+        self.offline_messages().iter().any(|k| s.contains(k))
+    }
+}
+
+/// 外部解析命令, 参数支持 `{platform}` `{room}` `{url}` 模板
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ResolverConfig {
+    pub kind: ResolverKind,
+    pub command: String,
+    // 为空时使用 `ResolverKind::default_args`
+    #[serde(default)]
+    pub args: Vec<String>,
+    // 适用的平台, 为空表示全部
+    #[serde(default)]
+    pub platforms: Vec<Platform>,
+    // true 时先于内置解析使用, 否则只在内置解析失败后使用
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl ResolverConfig {
+    pub fn applies_to(&self, platform: Platform) -> bool {
+        self.platforms.is_empty() || self.platforms.contains(&platform)
+    }
+
+    fn args(&self, platform: Platform, room_id: &str) -> anyhow::Result<Vec<String>> {
+        let vars = HashMap::from([
+            ("platform".to_string(), platform.as_seam_arg().to_string()),
+            ("room".to_string(), room_id.to_string()),
+            ("url".to_string(), platform.room_url(room_id)),
+        ]);
+        let args = if self.args.is_empty() {
+            self.kind.default_args()
+        } else {
+            self.args.clone()
+        };
+        args.iter()
+            .map(|a| strfmt::strfmt(a, &vars).map_err(|e| anyhow::anyhow!("{}: {}", a, e)))
+            .collect()
+    }
+
+    /// `Ok(None)` 表示未开播
+    pub async fn resolve(
+        &self,
+        platform: Platform,
+        room_id: &str,
+        proxy: Option<&str>,
+        timeout: Duration,
+    ) -> anyhow::Result<Option<SeamInfo>> {
+        let mut cmd = process::Command::new(&self.command);
+        cmd.args(self.args(platform, room_id)?)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(p) = proxy {
+            cmd.envs(proxy::proxy_env(p));
+        }
+        let out = tokio::time::timeout(timeout, cmd.output())
+            .await
+            .map_err(|_| anyhow::anyhow!("{} timeout", self.command))??;
+
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if !out.status.success() && out.stdout.iter().all(u8::is_ascii_whitespace) {
+            if self.kind.is_offline_message(&stderr) {
+                return Ok(None);
+            }
+            anyhow::bail!("{} exit {}: {}", self.command, out.status, stderr.trim());
+        }
+        parse_output(self.kind, &out.stdout)
+    }
+}
+
+pub fn parse_output(kind: ResolverKind, data: &[u8]) -> anyhow::Result<Option<SeamInfo>> {
+    match kind {
+        ResolverKind::Seam => {
+            let info: SeamInfo = serde_json::from_slice(data)?;
+            Ok(Some(info).filter(|i| i.nodes.as_ref().map_or(false, |n| !n.is_empty())))
+        }
+        ResolverKind::Streamlink => parse_streamlink(data),
+        ResolverKind::YtDlp => parse_ytdlp(data),
+    }
+}
+
+fn parse_streamlink(data: &[u8]) -> anyhow::Result<Option<SeamInfo>> {
+    #[derive(Deserialize)]
+    struct Output {
+        error: Option<String>,
+        #[serde(default)]
+        metadata: Metadata,
+        #[serde(default)]
+        streams: HashMap<String, Stream>,
+    }
+    #[derive(Deserialize, Default)]
+    struct Metadata {
+        title: Option<String>,
+        author: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Stream {
+        #[serde(rename = "type")]
+        typ: String,
+        url: String,
+    }
+
+    let out: Output = serde_json::from_slice(data)?;
+    if let Some(e) = out.error {
+        if ResolverKind::Streamlink.is_offline_message(&e) {
+            return Ok(None);
+        }
+        anyhow::bail!(e);
+    }
+    if out.streams.is_empty() {
+        return Ok(None);
+    }
+    // best 放在最前, 其余按名称排序, 去掉 worst 之类的别名重复
+    let mut names: Vec<_> = out.streams.keys().cloned().collect();
+    names.sort_by_key(|n| (n != "best", n.clone()));
+    let mut nodes: Vec<Node> = vec![];
+    for n in names {
+        let s = &out.streams[&n];
+        if nodes.iter().any(|v| v.url == s.url) {
+            continue;
+        }
+        nodes.push(Node {
+            format: format!("{}({})", s.typ, n),
+            url: s.url.clone(),
+        });
+    }
+    Ok(Some(SeamInfo {
+        title: out.metadata.title.unwrap_or_default(),
+        nodes: Some(nodes),
+        cover: None,
+        avatar: None,
+        nickname: out.metadata.author,
+    }))
+}
+
+fn parse_ytdlp(data: &[u8]) -> anyhow::Result<Option<SeamInfo>> {
+    #[derive(Deserialize)]
+    struct Output {
+        #[serde(default)]
+        title: String,
+        is_live: Option<bool>,
+        thumbnail: Option<String>,
+        uploader: Option<String>,
+        url: Option<String>,
+        ext: Option<String>,
+        #[serde(default)]
+        formats: Vec<Format>,
+    }
+    #[derive(Deserialize)]
+    struct Format {
+        url: String,
+        ext: Option<String>,
+        format_id: Option<String>,
+    }
+
+    let out: Output = serde_json::from_slice(data)?;
+    if out.is_live == Some(false) {
+        return Ok(None);
+    }
+    let mut nodes: Vec<Node> = out
+        .formats
+        .into_iter()
+        .rev()
+        .map(|f| Node {
+            format: match f.format_id {
+                Some(id) => format!("{}({})", f.ext.unwrap_or_default(), id),
+                None => f.ext.unwrap_or_default(),
+            },
+            url: f.url,
+        })
+        .collect();
+    if let Some(url) = out.url {
+        if !nodes.iter().any(|n| n.url == url) {
+            nodes.insert(
+                0,
+                Node {
+                    format: out.ext.unwrap_or_default(),
+                    url,
+                },
+            );
+        }
+    }
+    if nodes.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(SeamInfo {
+        title: out.title,
+        nodes: Some(nodes),
+        cover: out.thumbnail,
+        avatar: None,
+        nickname: out.uploader,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_output, ResolverConfig, ResolverKind};
+    use crate::app::model::Platform;
+
+    #[test]
+    fn test_parse_streamlink() {
+        let info = parse_output(
+            ResolverKind::Streamlink,
+            br#"{
+                "plugin": "bilibili",
+                "metadata": {"id": "1", "author": "a", "category": null, "title": "ttt"},
+                "streams": {
+                    "worst": {"type": "hls", "url": "http://x/low.m3u8"},
+                    "best": {"type": "hls", "url": "http://x/high.m3u8"},
+                    "720p": {"type": "hls", "url": "http://x/high.m3u8"},
+                    "480p": {"type": "hls", "url": "http://x/low.m3u8"}
+                }
+            }"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(info.title, "ttt");
+        assert_eq!(info.nickname.as_deref(), Some("a"));
+        let nodes = info.nodes.unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].url, "http://x/high.m3u8");
+        assert_eq!(nodes[0].format, "hls(best)");
+
+        let off = parse_output(
+            ResolverKind::Streamlink,
+            br#"{"error": "No playable streams found on this URL: https://live.bilibili.com/1"}"#,
+        )
+        .unwrap();
+        assert!(off.is_none());
+    }
+
+    #[test]
+    fn test_offline_message() {
+        assert!(ResolverKind::YtDlp
+            .is_offline_message("ERROR: [twitch:stream] abc: The channel is not currently live"));
+        assert!(ResolverKind::Seam.is_offline_message("主播未开播"));
+        // 网络错误不是未开播
+        assert!(!ResolverKind::Streamlink.is_offline_message("error: proxy offline"));
+        assert!(!ResolverKind::YtDlp.is_offline_message("ERROR: network offline"));
+        // 只匹配对应工具的信息
+        assert!(!ResolverKind::Seam.is_offline_message("No playable streams found on this URL"));
+    }
+
+    #[test]
+    fn test_parse_ytdlp() {
+        let info = parse_output(
+            ResolverKind::YtDlp,
+            br#"{
+                "title": "yyy",
+                "is_live": true,
+                "thumbnail": "http://x/cover.jpg",
+                "formats": [
+                    {"url": "http://x/360.m3u8", "ext": "mp4", "format_id": "360p"},
+                    {"url": "http://x/1080.m3u8", "ext": "mp4", "format_id": "1080p"}
+                ]
+            }"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(info.title, "yyy");
+        assert_eq!(info.cover.as_deref(), Some("http://x/cover.jpg"));
+        assert_eq!(info.nodes.unwrap()[0].format, "mp4(1080p)");
+
+        let off = parse_output(ResolverKind::YtDlp, br#"{"title": "y", "is_live": false}"#);
+        assert!(off.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_seam() {
+        let info = parse_output(
+            ResolverKind::Seam,
+            br#"{"title": "s", "nodes": [{"format": "flv", "url": "http://x/a.flv"}]}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(info.nodes.unwrap()[0].url, "http://x/a.flv");
+        let off = parse_output(ResolverKind::Seam, br#"{"title": "", "nodes": []}"#).unwrap();
+        assert!(off.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_command() {
+        let r = ResolverConfig {
+            kind: ResolverKind::Seam,
+            command: "sh".into(),
+            args: vec![
+                "-c".into(),
+                r#"echo '{{"title": "'$0'", "nodes": [{{"format": "flv", "url": "'$1'"}}]}}'"#
+                    .into(),
+                "{platform}".into(),
+                "{url}".into(),
+            ],
+            platforms: vec![],
+            primary: false,
+        };
+        let info = r
+            .resolve(Platform::BiliBili, "6", None, Duration::from_secs(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.title, "bili");
+        assert_eq!(info.nodes.unwrap()[0].url, "https://live.bilibili.com/6");
+
+        let fail = ResolverConfig {
+            command: "sh".into(),
+            args: vec!["-c".into(), "echo boom >&2; exit 1".into()],
+            ..r.clone()
+        };
+        let err = fail
+            .resolve(Platform::BiliBili, "6", None, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("boom"));
+
+        let offline = ResolverConfig {
+            args: vec!["-c".into(), "echo '主播未开播' >&2; exit 1".into()],
+            ..fail.clone()
+        };
+        assert!(offline
+            .resolve(Platform::BiliBili, "6", None, Duration::from_secs(5))
+            .await
+            .unwrap()
+            .is_none());
+
+        // 只含 offline 的其它错误不算未开播
+        let generic = ResolverConfig {
+            args: vec!["-c".into(), "echo 'room is offline' >&2; exit 1".into()],
+            ..fail
+        };
+        let err = generic
+            .resolve(Platform::BiliBili, "6", None, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("room is offline"));
+    }
+}
@@ -1,83 +1,611 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use seam_core::{error::SeamError, live::Format};
-use tokio::sync::{mpsc, Mutex, Semaphore};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    process,
+    sync::{mpsc, Mutex, OwnedSemaphorePermit, Semaphore},
+    task::AbortHandle,
+};
 
-use crate::app::model::{self, ShowType};
+use crate::app::model::{Platform, ShowType};
 
 use super::{
+    credentials::Credentials,
     model::{AnchorInfo, Node, SeamInfo},
+    probe::probe,
+    proxy::proxy_env,
+    resolver::ResolverConfig,
     uitl::AppConfig,
 };
 
+/// 通过代理查询时子进程的参数, 见 `child_query`
+pub const QUERY_ARG: &str = "--seam-query";
+const COOKIE_ENV: &str = "SEAMUI_QUERY_COOKIE";
+const RESULT_PREFIX: &str = "seamui-result ";
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ServerConfig {
+    // 全局并发查询数
+    pub concurrency: usize,
+    // 单个平台的并发查询数, 未配置的平台只受全局限制
+    pub platform_concurrency: HashMap<Platform, usize>,
+    pub timeout_secs: u64,
+    // 失败后的重试次数, 未开播不重试
+    pub retries: u32,
+    pub backoff_ms: u64,
+    pub backoff_max_ms: u64,
+    // 播放时查询结果超过该秒数会重新查询, 直播流地址中的 token 会过期, 0 为不重新查询
+    pub stream_ttl_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 5,
+            platform_concurrency: HashMap::new(),
+            timeout_secs: 15,
+            retries: 2,
+            backoff_ms: 500,
+            backoff_max_ms: 8000,
+            stream_ttl_secs: 300,
+        }
+    }
+}
+
+/// 第 `attempt` 次重试前的等待时间, 指数增长, 一半固定一半随机
+pub fn backoff(cfg: &ServerConfig, attempt: u32, jitter: f64) -> Duration {
+    let exp = cfg
+        .backoff_ms
+        .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX))
+        .min(cfg.backoff_max_ms);
+    let half = exp / 2;
+    Duration::from_millis(half + ((exp - half) as f64 * jitter.clamp(0.0, 1.0)) as u64)
+}
+
+pub fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    nanos as f64 / 1_000_000_000.0
+}
+
+struct Limiter {
+    size: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Limiter {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            semaphore: Arc::new(Semaphore::new(size)),
+        }
+    }
+
+    // 配置变化时直接换新的信号量, 旧任务持有的许可自然释放
+    fn resize(&mut self, size: usize) -> Arc<Semaphore> {
+        let size = size.max(1);
+        if size != self.size {
+            *self = Self::new(size);
+        }
+        self.semaphore.clone()
+    }
+}
+
+/// 查询许可, 先取平台许可再取全局许可, 等待繁忙平台的任务不会占用全局许可
+struct Permits {
+    global: Arc<Semaphore>,
+    platform: Option<Arc<Semaphore>>,
+    held: Option<(Option<OwnedSemaphorePermit>, OwnedSemaphorePermit)>,
+}
+
+impl Permits {
+    async fn acquire(&mut self) {
+        let p = match &self.platform {
+            Some(s) => Some(s.clone().acquire_owned().await.unwrap()),
+            None => None,
+        };
+        let g = self.global.clone().acquire_owned().await.unwrap();
+        self.held = Some((p, g));
+    }
+
+    // 重试等待期间释放, 让其他任务先查询
+    fn release(&mut self) {
+        self.held = None;
+    }
+}
+
+#[derive(Debug)]
+pub enum ServerTask {
+    Query(AnchorInfo, AppConfig),
+    // 取消该 key 的查询, 见 `AnchorInfo::key`
+    Cancel(String),
+    // 更新各平台 cookie
+    Credentials(Credentials),
+}
+
+/// 发给 ui 的查询进度
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+    // 取得并发许可, 开始查询该 key
+    Started(String),
+    Finished(AnchorInfo),
+}
+
+/// 查询队列状态, 可在 ui 线程读取
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    // 等待并发许可的查询
+    pending: AtomicUsize,
+    // 正在查询
+    running: AtomicUsize,
+}
+
+impl ServerStats {
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.pending() + self.running()
+    }
+}
+
+// 任务被取消时 future 直接被丢弃, 用 drop 保证计数正确
+struct Gauge(Arc<ServerStats>, bool);
+
+impl Gauge {
+    fn new(stats: Arc<ServerStats>, running: bool) -> Self {
+        let g = Gauge(stats, running);
+        g.counter().fetch_add(1, Ordering::Relaxed);
+        g
+    }
+
+    fn counter(&self) -> &AtomicUsize {
+        if self.1 {
+            &self.0.running
+        } else {
+            &self.0.pending
+        }
+    }
+}
+
+impl Drop for Gauge {
+    fn drop(&mut self) {
+        self.counter().fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+type InFlight = Arc<std::sync::Mutex<HashMap<String, (u64, AbortHandle)>>>;
+
 pub struct SeamServer {
-    result_sender: Arc<Mutex<mpsc::UnboundedSender<AnchorInfo>>>,
-    task_receiver: mpsc::UnboundedReceiver<(AnchorInfo, AppConfig)>,
+    result_sender: Arc<Mutex<mpsc::UnboundedSender<TaskEvent>>>,
+    task_receiver: mpsc::UnboundedReceiver<ServerTask>,
+    stats: Arc<ServerStats>,
+    // 排队中和查询中的任务, 同一个房间只会有一个
+    in_flight: InFlight,
 }
 
 impl SeamServer {
     pub fn new(
-        result_sender: mpsc::UnboundedSender<AnchorInfo>,
-        task_receiver: mpsc::UnboundedReceiver<(AnchorInfo, AppConfig)>,
+        result_sender: mpsc::UnboundedSender<TaskEvent>,
+        task_receiver: mpsc::UnboundedReceiver<ServerTask>,
     ) -> Self {
         SeamServer {
             result_sender: Arc::new(Mutex::new(result_sender)),
             task_receiver,
+            stats: Default::default(),
+            in_flight: Default::default(),
         }
     }
 
+    pub fn stats(&self) -> Arc<ServerStats> {
+        self.stats.clone()
+    }
+
     pub async fn run(mut self) {
-        let semaphore = Arc::new(Semaphore::new(5));
+        let mut global = Limiter::new(ServerConfig::default().concurrency);
+        let mut platforms: HashMap<Platform, Limiter> = HashMap::new();
+        let next_id = AtomicU64::new(0);
+        let mut credentials = Credentials::default();
 
-        while let Some((mut info, _cfg)) = self.task_receiver.recv().await {
+        while let Some(task) = self.task_receiver.recv().await {
+            let (mut info, cfg) = match task {
+                ServerTask::Query(info, cfg) => (info, cfg),
+                ServerTask::Credentials(c) => {
+                    credentials = c;
+                    continue;
+                }
+                ServerTask::Cancel(key) => {
+                    if let Some((_, h)) = self.in_flight.lock().unwrap().remove(&key) {
+                        log::info!("cancel query {}", key);
+                        h.abort();
+                    }
+                    continue;
+                }
+            };
+
+            let key = info.key();
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.contains_key(&key) {
+                log::debug!("query {} already in flight", key);
+                continue;
+            }
+
+            let platform = info.platform;
+            let cookie = platform
+                .and_then(|p| credentials.cookie(p))
+                .map(str::to_string);
+            let global_sem = global.resize(cfg.server.concurrency);
+            let platform_sem = platform.and_then(|p| {
+                cfg.server.platform_concurrency.get(&p).map(|n| {
+                    platforms
+                        .entry(p)
+                        .or_insert_with(|| Limiter::new(*n))
+                        .resize(*n)
+                })
+            });
             let sender = self.result_sender.clone();
-            let guard = semaphore.clone().acquire_owned().await.unwrap();
-            tokio::spawn(async move {
-                let _g = guard;
-                let output = info.platform.unwrap().get_live(&info.room_id).await;
-
-                match output {
-                    Ok(out) => {
-                        let nodes: Vec<_> = out
-                            .urls
-                            .into_iter()
-                            .map(|u| -> Node {
-                                let str = match u.format {
-                                    Format::Flv => "flv".to_string(),
-                                    Format::M3U => "m3u".to_string(),
-                                    Format::Rtmp => "rtmp".to_owned(),
-                                    Format::Other(s) => s,
-                                };
-                                Node {
-                                    format: str,
-                                    url: u.url,
-                                }
-                            })
-                            .collect();
-                        let seam_info = SeamInfo {
-                            title: out.title,
-                            nodes: Some(nodes),
-                        };
-                        info.show_type = Some(ShowType::On(seam_info));
-                        sender.lock().await.send(info).expect("send err");
+            let stats = self.stats.clone();
+            let done = self.in_flight.clone();
+            let id = next_id.fetch_add(1, Ordering::Relaxed);
+            let task_key = key.clone();
+
+            let pending = Gauge::new(stats.clone(), false);
+            let mut permits = Permits {
+                global: global_sem,
+                platform: platform_sem,
+                held: None,
+            };
+            let handle = tokio::spawn(async move {
+                permits.acquire().await;
+                drop(pending);
+                let running = Gauge::new(stats, true);
+                sender
+                    .lock()
+                    .await
+                    .send(TaskEvent::Started(task_key.clone()))
+                    .expect("send err");
+                info.show_type = Some(match (&info.url, platform) {
+                    (Some(url), _) => {
+                        let timeout = Duration::from_secs(cfg.server.timeout_secs.max(1));
+                        probe(url, cfg.proxy.for_platform(None), timeout).await
+                    }
+                    (None, Some(p)) => {
+                        query(p, &info.room_id, cookie.as_deref(), &cfg, &mut permits).await
+                    }
+                    (None, None) => ShowType::Error("no platform".into()),
+                });
+                info.updated_at = Some(SystemTime::now());
+                drop(running);
+
+                {
+                    let mut done = done.lock().unwrap();
+                    if !matches!(done.get(&task_key), Some((i, _)) if *i == id) {
+                        // 已被取消
+                        return;
                     }
-                    Err(e) => match e {
-                        SeamError::None => {
-                            log::info!("seam query result off");
-                            info.show_type = Some(model::ShowType::Off);
-                            sender.lock().await.send(info).expect("send err");
-                        }
-                        _ => {
-                            log::error!(
-                                "seam query {:?} {} err {:?}",
-                                info.platform,
-                                info.room_id,
-                                e
-                            );
-                        }
-                    },
+                    done.remove(&task_key);
                 }
+                sender
+                    .lock()
+                    .await
+                    .send(TaskEvent::Finished(info))
+                    .expect("send err");
             });
+            in_flight.insert(key, (id, handle.abort_handle()));
         }
     }
 }
+
+// primary 的外部解析先于 seam_core, 其余只在 seam_core 查询失败后按顺序尝试
+async fn query(
+    platform: Platform,
+    room_id: &str,
+    cookie: Option<&str>,
+    cfg: &AppConfig,
+    permits: &mut Permits,
+) -> ShowType {
+    let timeout = Duration::from_secs(cfg.server.timeout_secs.max(1));
+    let proxy = cfg.proxy.for_platform(Some(platform));
+    let (primary, fallback): (Vec<_>, Vec<_>) = cfg
+        .resolvers
+        .iter()
+        .filter(|r| r.applies_to(platform))
+        .partition(|r| r.primary);
+
+    let mut last_err = None;
+    for r in primary {
+        match resolve(r, platform, room_id, proxy, timeout).await {
+            ShowType::Error(e) => last_err = Some(e),
+            v => return v,
+        }
+    }
+    match query_seam(platform, room_id, cookie, proxy, &cfg.server, permits).await {
+        ShowType::Error(e) => last_err = Some(e),
+        v => return v,
+    }
+    for r in fallback {
+        match resolve(r, platform, room_id, proxy, timeout).await {
+            ShowType::Error(e) => last_err = Some(e),
+            v => return v,
+        }
+    }
+    ShowType::Error(last_err.unwrap_or_default())
+}
+
+async fn resolve(
+    r: &ResolverConfig,
+    platform: Platform,
+    room_id: &str,
+    proxy: Option<&str>,
+    timeout: Duration,
+) -> ShowType {
+    match r.resolve(platform, room_id, proxy, timeout).await {
+        Ok(Some(info)) => ShowType::On(info),
+        Ok(None) => ShowType::Off,
+        Err(e) => {
+            log::warn!("{} query {:?} {} err {}", r.command, platform, room_id, e);
+            ShowType::Error(e.to_string())
+        }
+    }
+}
+
+async fn query_seam(
+    platform: Platform,
+    room_id: &str,
+    cookie: Option<&str>,
+    proxy: Option<&str>,
+    cfg: &ServerConfig,
+    permits: &mut Permits,
+) -> ShowType {
+    let timeout = Duration::from_secs(cfg.timeout_secs.max(1));
+    let mut attempt = 0;
+    loop {
+        let err = match tokio::time::timeout(timeout, fetch(platform, room_id, cookie, proxy)).await
+        {
+            Ok(Ok(v)) => return v,
+            Ok(Err(e)) => e,
+            Err(_) => format!("timeout after {}s", timeout.as_secs()),
+        };
+
+        if attempt >= cfg.retries {
+            log::error!("seam query {:?} {} err {}", platform, room_id, err);
+            return ShowType::Error(err);
+        }
+        let wait = backoff(cfg, attempt, jitter());
+        log::warn!(
+            "seam query {:?} {} err {}, retry in {:?}",
+            platform,
+            room_id,
+            err,
+            wait
+        );
+        permits.release();
+        tokio::time::sleep(wait).await;
+        permits.acquire().await;
+        attempt += 1;
+    }
+}
+
+/// seam_core 在内部创建 http 客户端, 无法按次设置代理。需要代理时在子进程中查询,
+/// 代理通过子进程的环境变量传入, 不修改本进程的环境变量
+async fn fetch(
+    platform: Platform,
+    room_id: &str,
+    cookie: Option<&str>,
+    proxy: Option<&str>,
+) -> Result<ShowType, String> {
+    let Some(proxy) = proxy else {
+        return fetch_local(platform, room_id, cookie).await;
+    };
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut cmd = process::Command::new(exe);
+    cmd.arg(QUERY_ARG);
+    fetch_in_child(cmd, platform, room_id, cookie, proxy).await
+}
+
+/// 未开播返回 `ShowType::Off`, 其余错误返回 Err 由调用方重试
+async fn fetch_local(
+    platform: Platform,
+    room_id: &str,
+    cookie: Option<&str>,
+) -> Result<ShowType, String> {
+    let out = match platform.get_live(room_id, cookie).await {
+        Ok(out) => out,
+        Err(SeamError::None) => {
+            log::info!("seam query result off");
+            return Ok(ShowType::Off);
+        }
+        Err(e) => return Err(format!("{:?}", e)),
+    };
+    let nodes: Vec<_> = out
+        .urls
+        .into_iter()
+        .map(|u| -> Node {
+            let str = match u.format {
+                Format::Flv => "flv".to_string(),
+                Format::M3U => "m3u".to_string(),
+                Format::Rtmp => "rtmp".to_owned(),
+                Format::Other(s) => s,
+            };
+            Node {
+                format: str,
+                url: u.url,
+            }
+        })
+        .collect();
+    let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+    Ok(ShowType::On(SeamInfo {
+        title: out.title,
+        nodes: Some(nodes),
+        cover: non_empty(out.cover),
+        avatar: non_empty(out.head),
+        nickname: non_empty(out.anchor),
+    }))
+}
+
+/// `cmd` 运行后调用 `child_query`, 结果从标准输出中带 `RESULT_PREFIX` 的一行读取
+async fn fetch_in_child(
+    mut cmd: process::Command,
+    platform: Platform,
+    room_id: &str,
+    cookie: Option<&str>,
+    proxy: &str,
+) -> Result<ShowType, String> {
+    cmd.arg(format!("{:?}", platform))
+        .arg(room_id)
+        .envs(proxy_env(proxy))
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    match cookie {
+        // cookie 不放在命令行参数中, 避免被其他进程看到
+        Some(c) => cmd.env(COOKIE_ENV, c),
+        None => cmd.env_remove(COOKIE_ENV),
+    };
+    let out = cmd.output().await.map_err(|e| e.to_string())?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix(RESULT_PREFIX))
+        .ok_or_else(|| {
+            format!(
+                "query process {}: {}",
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            )
+        })
+        .and_then(|l| serde_json::from_str(l).map_err(|e| e.to_string()))?
+}
+
+/// 子进程入口, `args` 为 `QUERY_ARG` 之后的平台和房间号, 只查询一次
+pub fn child_query(args: &[String]) -> anyhow::Result<()> {
+    let [platform, room_id] = args else {
+        anyhow::bail!("usage: seamui {} <platform> <room>", QUERY_ARG);
+    };
+    let platform: Platform = platform.parse()?;
+    let cookie = std::env::var(COOKIE_ENV).ok();
+    let rt = tokio::runtime::Runtime::new()?;
+    let r = rt.block_on(fetch_local(platform, room_id, cookie.as_deref()));
+    println!("{}{}", RESULT_PREFIX, serde_json::to_string(&r)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::sync::Arc;
+
+    use tokio::sync::Semaphore;
+
+    use super::{backoff, child_query, fetch_in_child, Permits, ServerConfig, COOKIE_ENV};
+    use crate::app::{
+        model::{Platform, ShowType},
+        test_http,
+    };
+
+    #[test]
+    fn test_backoff() {
+        let cfg = ServerConfig::default();
+        assert_eq!(backoff(&cfg, 0, 0.0), Duration::from_millis(250));
+        assert_eq!(backoff(&cfg, 0, 1.0), Duration::from_millis(500));
+        assert_eq!(backoff(&cfg, 2, 0.0), Duration::from_millis(1000));
+        // 超过上限后不再增长
+        assert_eq!(backoff(&cfg, 10, 1.0), Duration::from_millis(8000));
+        assert_eq!(backoff(&cfg, 200, 1.0), Duration::from_millis(8000));
+    }
+
+    #[tokio::test]
+    async fn test_permits_order() {
+        let global = Arc::new(Semaphore::new(1));
+        let platform = Arc::new(Semaphore::new(1));
+        // 平台已满
+        let busy = platform.clone().acquire_owned().await.unwrap();
+        let mut waiting = Permits {
+            global: global.clone(),
+            platform: Some(platform.clone()),
+            held: None,
+        };
+        let t = tokio::spawn(async move {
+            waiting.acquire().await;
+            waiting
+        });
+        tokio::task::yield_now().await;
+        // 等待平台许可时不占用全局许可, 其他平台可以查询
+        assert_eq!(global.available_permits(), 1);
+        let mut other = Permits {
+            global: global.clone(),
+            platform: None,
+            held: None,
+        };
+        other.acquire().await;
+        other.release();
+
+        drop(busy);
+        let mut waiting = t.await.unwrap();
+        assert_eq!(global.available_permits(), 0);
+        waiting.release();
+        assert_eq!(global.available_permits(), 1);
+        assert_eq!(platform.available_permits(), 1);
+    }
+
+    const CHILD_TEST: &str = "app::server::tests::child_entry";
+
+    // 测试程序重新运行自身作为查询子进程, fetch_in_child 追加的平台和房间号跟在测试名后面,
+    // 收到的参数和 cookie 写入 SEAMUI_TEST_CHILD 指定的文件
+    #[test]
+    fn child_entry() {
+        let Some(record) = std::env::var_os("SEAMUI_TEST_CHILD") else {
+            return;
+        };
+        let args: Vec<String> = std::env::args()
+            .skip_while(|a| a != CHILD_TEST)
+            .skip(1)
+            .collect();
+        let cookie = std::env::var(COOKIE_ENV).ok();
+        std::fs::write(record, serde_json::to_vec(&(&args, &cookie)).unwrap()).unwrap();
+        child_query(&args).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_through_proxy() {
+        let proxy = test_http::spawn(200, b"proxied").await;
+        let record = std::env::temp_dir().join(format!("seamui-child-{}", std::process::id()));
+        let mut cmd = tokio::process::Command::new(std::env::current_exe().unwrap());
+        cmd.args(["--exact", "--nocapture", "--test-threads=1"])
+            .arg(CHILD_TEST)
+            .env("SEAMUI_TEST_CHILD", &record);
+        let r = fetch_in_child(cmd, Platform::BiliBili, "42", Some("sid=1"), &proxy.url("")).await;
+
+        // 子进程收到 fetch_in_child 传入的平台、房间号和 cookie
+        let (args, cookie): (Vec<String>, Option<String>) =
+            serde_json::from_slice(&std::fs::read(&record).unwrap()).unwrap();
+        std::fs::remove_file(&record).ok();
+        assert_eq!(args, ["BiliBili", "42"]);
+        assert_eq!(cookie.as_deref(), Some("sid=1"));
+        // 代理返回的不是平台的响应, 查询失败, 但请求经过了代理
+        assert!(!matches!(r, Ok(ShowType::On(_))), "{:?}", r);
+        let reqs = proxy.requests.lock().unwrap();
+        assert!(!reqs.is_empty());
+        assert!(
+            reqs[0].starts_with("CONNECT ") || reqs[0].starts_with("GET http://"),
+            "{}",
+            reqs[0]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! 测试用的本地 HTTP 服务, 记录收到的请求并返回固定响应
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub struct StandIn {
+    pub addr: SocketAddr,
+    pub requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StandIn {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn hits(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+pub async fn spawn(status: u16, body: &'static [u8]) -> StandIn {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(vec![]));
+    let reqs = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let reqs = reqs.clone();
+            tokio::spawn(handle(stream, status, body, reqs));
+        }
+    });
+    StandIn { addr, requests }
+}
+
+// 先记录请求再响应, 客户端收到响应时请求一定已经记录
+async fn handle(
+    mut stream: TcpStream,
+    status: u16,
+    body: &[u8],
+    requests: Arc<Mutex<Vec<String>>>,
+) -> Option<()> {
+    let mut buf = vec![];
+    let mut chunk = [0u8; 1024];
+    let head_end = loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(p) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break p + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < head_end + content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    requests
+        .lock()
+        .unwrap()
+        .push(String::from_utf8_lossy(&buf).to_string());
+
+    let resp = format!(
+        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(resp.as_bytes()).await.ok()?;
+    stream.write_all(body).await.ok()?;
+    stream.shutdown().await.ok()
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use iced::{theme::Palette, widget::container, Color, Theme};
+use serde::{Deserialize, Serialize};
+
+use super::i18n::tr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ThemeMode {
+    #[default]
+    Light,
+    Dark,
+    // 跟随系统, 检测不到时使用浅色
+    System,
+    // 从文件加载调色板
+    Custom,
+}
+
+impl ThemeMode {
+    pub const ALL: &'static [ThemeMode] = &[
+        ThemeMode::Light,
+        ThemeMode::Dark,
+        ThemeMode::System,
+        ThemeMode::Custom,
+    ];
+
+    pub fn as_ui_text(&self) -> &'static str {
+        match self {
+            ThemeMode::Light => tr("theme.light"),
+            ThemeMode::Dark => tr("theme.dark"),
+            ThemeMode::System => tr("theme.system"),
+            ThemeMode::Custom => tr("theme.custom"),
+        }
+    }
+
+    /// `system_dark` 为缓存的系统主题, 见 `system_dark`
+    pub fn resolve(&self, custom: Option<&Palette>, system_dark: bool) -> Theme {
+        match self {
+            ThemeMode::Light => Theme::Light,
+            ThemeMode::Dark => Theme::Dark,
+            ThemeMode::System if system_dark => Theme::Dark,
+            ThemeMode::System => Theme::Light,
+            ThemeMode::Custom => match custom {
+                Some(p) => Theme::custom(*p),
+                None => Theme::Light,
+            },
+        }
+    }
+}
+
+/// 查询系统是否为深色模式, 每次调用都会访问系统设置, 不要在 view 中调用
+pub fn system_dark() -> bool {
+    matches!(dark_light::detect(), dark_light::Mode::Dark)
+}
+
+impl std::fmt::Display for ThemeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ui_text())
+    }
+}
+
+/// 键盘选中的条目
+pub struct SelectedItem;
+
+impl container::StyleSheet for SelectedItem {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let palette = style.extended_palette();
+        container::Appearance {
+            text_color: None,
+            background: Some(palette.background.weak.color.into()),
+            border_radius: 2.0,
+            border_width: 2.0,
+            border_color: palette.primary.strong.color,
+        }
+    }
+}
+
+/// 自定义调色板文件, 颜色格式为 `#rrggbb`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PaletteFile {
+    pub background: String,
+    pub text: String,
+    pub primary: String,
+    pub success: String,
+    pub danger: String,
+}
+
+impl PaletteFile {
+    pub fn into_palette(self) -> anyhow::Result<Palette> {
+        Ok(Palette {
+            background: parse_hex(&self.background)?,
+            text: parse_hex(&self.text)?,
+            primary: parse_hex(&self.primary)?,
+            success: parse_hex(&self.success)?,
+            danger: parse_hex(&self.danger)?,
+        })
+    }
+}
+
+pub fn load_palette(path: impl AsRef<Path>) -> anyhow::Result<Palette> {
+    let data = std::fs::read(path)?;
+    serde_json::from_slice::<PaletteFile>(&data)?.into_palette()
+}
+
+fn parse_hex(s: &str) -> anyhow::Result<Color> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        anyhow::bail!("invalid color {:?}", s);
+    }
+    let v = u32::from_str_radix(hex, 16)?;
+    Ok(Color::from_rgb8(
+        (v >> 16) as u8,
+        ((v >> 8) & 0xff) as u8,
+        (v & 0xff) as u8,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaletteFile;
+
+    #[test]
+    fn test_palette_file() {
+        let p = serde_json::from_str::<PaletteFile>(
+            r##"
+        {
+            "background": "#202225",
+            "text": "#ffffff",
+            "primary": "#5865f2",
+            "success": "#3ba55c",
+            "danger": "#ed4245"
+        }"##,
+        )
+        .unwrap()
+        .into_palette()
+        .unwrap();
+        assert_eq!(p.text, iced::Color::WHITE);
+
+        let bad = PaletteFile {
+            background: "#fff".into(),
+            text: "#ffffff".into(),
+            primary: "#ffffff".into(),
+            success: "#ffffff".into(),
+            danger: "#ffffff".into(),
+        };
+        assert!(bad.into_palette().is_err());
+    }
+}
@@ -1,20 +1,122 @@
-use std::path::PathBuf;
+use std::{
+    future::Future,
+    ops::RangeInclusive,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use iced::Font;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use tokio::process;
 
-use super::model::{self, AnchorInfo};
+use super::{
+    hooks::HookConfig,
+    i18n::{tr, Lang},
+    keymap::KeyBindings,
+    model::{self, AnchorInfo},
+    mpv, player, profile,
+    proxy::{self, ProxyConfig},
+    resolver::ResolverConfig,
+    server::ServerConfig,
+    theme::ThemeMode,
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct AppConfig {
     pub player_path: String,
+    // 播放器参数, 见 `player::command_args`
+    #[serde(default)]
+    pub player_args: Vec<String>,
+    // 播放器是 mpv 时通过 IPC 在已打开的窗口中切换线路
+    #[serde(default = "default_mpv_ipc")]
+    pub mpv_ipc: bool,
+    #[serde(default)]
+    pub theme: ThemeMode,
+    #[serde(default)]
+    pub custom_theme_path: String,
+    #[serde(default)]
+    pub lang: Lang,
+    #[serde(default)]
+    pub keybindings: KeyBindings,
+    #[serde(default)]
+    pub window: WindowConfig,
+    // 紧凑模式不显示封面
+    #[serde(default)]
+    pub compact_mode: bool,
+    #[serde(default = "default_image_cache_hours")]
+    pub image_cache_hours: u64,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    // 外部解析命令, 按配置顺序尝试
+    #[serde(default)]
+    pub resolvers: Vec<ResolverConfig>,
+    // 对所有主播生效的开播状态 hook
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+}
+
+fn default_image_cache_hours() -> u64 {
+    24
+}
+
+fn default_mpv_ipc() -> bool {
+    true
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {
             player_path: "mpv".into(),
+            player_args: vec![],
+            mpv_ipc: default_mpv_ipc(),
+            theme: ThemeMode::default(),
+            custom_theme_path: "".into(),
+            lang: Lang::default(),
+            keybindings: KeyBindings::default(),
+            window: WindowConfig::default(),
+            compact_mode: false,
+            image_cache_hours: default_image_cache_hours(),
+            server: ServerConfig::default(),
+            proxy: ProxyConfig::default(),
+            resolvers: vec![],
+            hooks: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    #[serde(deserialize_with = "clamp_scale")]
+    pub scale_factor: f64,
+}
+
+/// 设置页允许的界面缩放范围
+pub const SCALE_RANGE: RangeInclusive<f64> = 0.5..=4.0;
+
+// 手动修改的配置文件中超出范围的缩放会导致界面无法使用
+fn clamp_scale<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    let v = f64::deserialize(d)?;
+    if v.is_nan() {
+        return Ok(1.0);
+    }
+    Ok(v.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end()))
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 500,
+            height: 800,
+            x: None,
+            y: None,
+            scale_factor: 1.0,
         }
     }
 }
@@ -27,17 +129,22 @@ pub struct SavedState {
 }
 
 impl SavedState {
+    /// 当前配置的 seamui.json, 见 `profile`
     pub fn path() -> std::path::PathBuf {
-        let mut p: PathBuf = directories_next::ProjectDirs::from("", "", "seamui")
-            .expect("cant find path")
-            .data_dir()
-            .into();
-        p.push("seamui.json");
-        p
+        profile::current().path()
     }
 
+    /// 文件不存在时(首次运行或新建的配置)返回默认内容
     pub async fn load() -> anyhow::Result<SavedState> {
-        let data = tokio::fs::read(Self::path()).await?;
+        Self::load_from(Self::path()).await
+    }
+
+    pub async fn load_from(path: PathBuf) -> anyhow::Result<SavedState> {
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
 
         let s: Self = match serde_json::from_slice(&data) {
             Ok(v) => v,
@@ -50,23 +157,96 @@ impl SavedState {
         Ok(s)
     }
 
-    pub async fn save(self) -> anyhow::Result<()> {
+    /// 启动时读取窗口配置, 此时还没有 tokio 运行时
+    pub fn load_blocking() -> anyhow::Result<SavedState> {
+        let data = std::fs::read(Self::path())?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// 检测外部修改时重新读取, 与 `load` 不同, 解析失败时返回错误
+    pub async fn read() -> anyhow::Result<SavedState> {
+        let data = tokio::fs::read(Self::path()).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// 配置文件的修改时间, 文件不存在时为 None
+    pub fn modified() -> Option<SystemTime> {
+        std::fs::metadata(Self::path()).ok()?.modified().ok()
+    }
+
+    /// 路径在调用时确定, 切换配置前发起的保存仍写入原来的文件。
+    /// 返回写入后的修改时间, 用来区分自己的写入和外部修改
+    pub fn save(self) -> impl Future<Output = anyhow::Result<SystemTime>> {
+        self.save_to(Self::path())
+    }
+
+    async fn save_to(self, path: PathBuf) -> anyhow::Result<SystemTime> {
         let data = serde_json::to_string_pretty(&self)?;
 
-        let path = Self::path();
         tokio::fs::create_dir_all(path.parent().expect("get dir")).await?;
         tokio::fs::File::create(&path).await?;
-        tokio::fs::write(path, data).await?;
-        Ok(())
+        tokio::fs::write(&path, data).await?;
+        Ok(tokio::fs::metadata(path).await?.modified()?)
+    }
+
+    /// 读取导出的主播列表, 也支持完整的 seamui.json
+    pub async fn import(path: PathBuf) -> anyhow::Result<Vec<AnchorInfo>> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Import {
+            List(Vec<AnchorInfo>),
+            State { anchors: Vec<AnchorInfo> },
+        }
+        let data = tokio::fs::read(path).await?;
+        Ok(match serde_json::from_slice(&data)? {
+            Import::List(v) => v,
+            Import::State { anchors } => anchors,
+        })
     }
+
+    /// 导出主播到数据目录下的 `export-<时间戳>.json`, 返回文件路径
+    pub async fn export(anchors: Vec<AnchorInfo>) -> anyhow::Result<PathBuf> {
+        let data = serde_json::to_string_pretty(&anchors)?;
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut path = Self::path();
+        path.set_file_name(format!("export-{}.json", secs));
+        tokio::fs::create_dir_all(path.parent().expect("get dir")).await?;
+        tokio::fs::write(&path, data).await?;
+        Ok(path)
+    }
+}
+
+/// 距离 `t` 的时间, 如 "3分钟前"
+pub fn format_age(t: SystemTime, now: SystemTime) -> String {
+    let secs = now.duration_since(t).map(|d| d.as_secs()).unwrap_or(0);
+    let (n, key) = match secs {
+        0..=59 => return tr("age.just_now").into(),
+        60..=3599 => (secs / 60, "age.minutes"),
+        3600..=86399 => (secs / 3600, "age.hours"),
+        _ => (secs / 86400, "age.days"),
+    };
+    tr(key).replace("{}", &n.to_string())
 }
 
 pub struct PlayState {}
 
 impl PlayState {
-    pub async fn play(node: model::Node, cfg: AppConfig) -> anyhow::Result<()> {
-        let _output = process::Command::new(cfg.player_path)
-            .arg(node.url)
+    /// `ipc` 为 mpv 的 IPC socket, 见 `mpv::ipc_arg`
+    pub async fn play(
+        node: model::Node,
+        cfg: AppConfig,
+        platform: Option<model::Platform>,
+        ipc: Option<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let mut cmd = process::Command::new(&cfg.player_path);
+        if let Some(p) = cfg.proxy.for_platform(platform) {
+            cmd.envs(proxy::proxy_env(p));
+        }
+        if let Some(socket) = ipc {
+            cmd.arg(mpv::ipc_arg(&socket));
+        }
+        let _output = cmd
+            .args(player::command_args(&cfg.player_args, &node.url))
             .output()
             .await?;
         Ok(())
@@ -78,3 +258,39 @@ pub const AWESOME: Font = Font::External {
     name: "Awesome",
     bytes: include_bytes!("../../static/fonts/fa-solid-900.ttf"),
 };
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{format_age, WindowConfig};
+
+    #[test]
+    fn test_scale_factor_clamped() {
+        let scale = |s: &str| {
+            serde_json::from_str::<WindowConfig>(&format!(r#"{{"scale_factor": {}}}"#, s))
+                .unwrap()
+                .scale_factor
+        };
+        assert_eq!(scale("1.5"), 1.5);
+        assert_eq!(scale("0"), 0.5);
+        assert_eq!(scale("-2"), 0.5);
+        assert_eq!(scale("1e300"), 4.0);
+        let default: WindowConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(default.scale_factor, 1.0);
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = SystemTime::now();
+        assert_eq!(format_age(now, now), "刚刚");
+        assert_eq!(format_age(now - Duration::from_secs(150), now), "2分钟前");
+        assert_eq!(format_age(now - Duration::from_secs(7200), now), "2小时前");
+        assert_eq!(
+            format_age(now - Duration::from_secs(3 * 86400), now),
+            "3天前"
+        );
+        // 时钟回拨
+        assert_eq!(format_age(now + Duration::from_secs(10), now), "刚刚");
+    }
+}
@@ -0,0 +1,167 @@
+//! 主播列表修改的撤销记录, 只保存在内存中, 本次运行期间删除的主播都可以恢复
+
+use std::time::{Duration, Instant};
+
+use super::{i18n::tr, model::AnchorInfo};
+
+// 撤销提示条显示的时间
+const BAR_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    // 删除, 记录原位置和内容, 按位置升序
+    Delete(Vec<(usize, AnchorInfo)>),
+    // 修改, 记录修改前的内容
+    Update(Vec<(usize, AnchorInfo)>),
+    // 从 from 移动到 to
+    Move(usize, usize),
+    // 导入时追加到末尾, 记录起始位置和数量
+    Import(usize, usize),
+}
+
+fn describe(items: &[(usize, AnchorInfo)]) -> String {
+    match items {
+        [(_, a)] => a.name.clone(),
+        _ => tr("undo.count").replace("{}", &items.len().to_string()),
+    }
+}
+
+impl Change {
+    pub fn as_ui_text(&self, list: &[AnchorInfo]) -> String {
+        match self {
+            Change::Delete(v) => format!("{} {}", tr("undo.deleted"), describe(v)),
+            Change::Update(v) => format!("{} {}", tr("undo.updated"), describe(v)),
+            Change::Move(_, to) => format!(
+                "{} {}",
+                tr("undo.moved"),
+                list.get(*to).map(|a| a.name.as_str()).unwrap_or_default()
+            ),
+            Change::Import(_, n) => format!(
+                "{} {}",
+                tr("undo.imported"),
+                tr("undo.count").replace("{}", &n.to_string())
+            ),
+        }
+    }
+
+    /// 在列表上撤销该修改, 返回需要选中的位置
+    pub fn revert(self, list: &mut Vec<AnchorInfo>) -> Option<usize> {
+        match self {
+            Change::Delete(v) => {
+                let mut first = None;
+                for (i, a) in v {
+                    let i = i.min(list.len());
+                    list.insert(i, a);
+                    first = first.or(Some(i));
+                }
+                first
+            }
+            Change::Update(v) => {
+                let mut first = None;
+                for (i, a) in v {
+                    if let Some(old) = list.get_mut(i) {
+                        *old = a;
+                        first = first.or(Some(i));
+                    }
+                }
+                first
+            }
+            Change::Move(from, to) => {
+                if from >= list.len() || to >= list.len() {
+                    return None;
+                }
+                let a = list.remove(to);
+                list.insert(from, a);
+                Some(from)
+            }
+            Change::Import(start, n) => {
+                let start = start.min(list.len());
+                let end = (start + n).min(list.len());
+                list.drain(start..end);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    changes: Vec<Change>,
+    // 最近一次修改的时间, 决定是否显示提示条
+    last_at: Option<Instant>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, change: Change) {
+        self.changes.push(change);
+        self.last_at = Some(Instant::now());
+    }
+
+    pub fn pop(&mut self) -> Option<Change> {
+        self.last_at = None;
+        self.changes.pop()
+    }
+
+    /// 需要显示提示条时返回最近一次修改
+    pub fn bar(&self, now: Instant) -> Option<&Change> {
+        let t = self.last_at?;
+        if now.duration_since(t) > BAR_TIMEOUT {
+            return None;
+        }
+        self.changes.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Change, UndoStack};
+    use crate::app::model::AnchorInfo;
+
+    fn anchor(name: &str) -> AnchorInfo {
+        AnchorInfo {
+            name: name.into(),
+            room_id: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn names(list: &[AnchorInfo]) -> Vec<&str> {
+        list.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut list = vec![anchor("a"), anchor("b"), anchor("c"), anchor("d")];
+        let mut stack = UndoStack::default();
+
+        list.extend([anchor("e"), anchor("f")]);
+        stack.push(Change::Import(4, 2));
+        assert_eq!(stack.pop().unwrap().revert(&mut list), None);
+        assert_eq!(names(&list), ["a", "b", "c", "d"]);
+
+        let c = list.remove(2);
+        let b = list.remove(1);
+        stack.push(Change::Delete(vec![(1, b), (2, c)]));
+        let old = list[0].clone();
+        list[0].name = "x".into();
+        stack.push(Change::Update(vec![(0, old)]));
+        let moved = list.remove(0);
+        list.insert(1, moved);
+        stack.push(Change::Move(0, 1));
+        assert_eq!(names(&list), ["d", "x"]);
+
+        let now = Instant::now();
+        assert!(stack.bar(now).is_some());
+        assert!(stack.bar(now + Duration::from_secs(60)).is_none());
+
+        assert_eq!(stack.pop().unwrap().revert(&mut list), Some(0));
+        assert_eq!(names(&list), ["x", "d"]);
+        assert!(stack.bar(now).is_none());
+        assert_eq!(stack.pop().unwrap().revert(&mut list), Some(0));
+        assert_eq!(stack.pop().unwrap().revert(&mut list), Some(1));
+        assert_eq!(names(&list), ["a", "b", "c", "d"]);
+        assert!(stack.pop().is_none());
+    }
+}
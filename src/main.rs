@@ -3,15 +3,33 @@ use iced::{window, Application, Settings};
 mod app;
 
 fn main() -> iced::Result {
-    env_logger::init();
+    app::init_logger();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(r) = app::child_query(&args) {
+        if let Err(e) = r {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Err(e) = app::init_location(args) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    let win = app::window_config();
+    let position = match (win.x, win.y) {
+        (Some(x), Some(y)) => window::Position::Specific(x, y),
+        _ => window::Position::default(),
+    };
     app::SeamUI::run(Settings {
         window: window::Settings {
-            size: (500, 800),
-
+            size: (win.width, win.height),
+            position,
             ..window::Settings::default()
         },
         default_font: Some(include_bytes!("../static/fonts/SIMHEI.TTF")),
         antialiasing: true,
+        exit_on_close_request: false,
         ..Settings::default()
     })
 }